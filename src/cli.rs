@@ -0,0 +1,1028 @@
+use solana_sdk::signature::{Keypair, Signer, read_keypair_file, write_keypair_file};
+use std::{collections::HashMap, fmt::Display, fs, io::Write, path::Path, process::ExitCode};
+
+use crate::pkg::{
+    account::{Account, AccountKind, AccountType, Summarizable, parse_pubkey},
+    errors::LedgerError,
+    faucet::FaucetPolicy,
+    interchange,
+    ledger::Ledger,
+    query::AccountQuery,
+    solana,
+};
+
+pub const USAGE: &str = "\
+usage: basic-ledger <command> <ledger> [arguments]
+
+commands:
+  init <ledger> [--faucet <authority keypair file>]
+  create-account <ledger> <kind> [--keypair <file>] [--payer <keypair file>] [--name <name>]
+                 [kind options]
+      wallet         [--balance <lamports>]
+      program        [--data <text> | --data-file <file> | --space <bytes>] [--executable]
+                     [--owner <program account>]
+      token_account  --mint <pubkey> [--delegate <pubkey>]
+      stake          --validator <pubkey> --amount <lamports>
+      mint           [--decimals <n>] [--mint-authority <pubkey>] [--freeze-authority <pubkey>]
+  transfer <ledger> <from keypair file> <to account> <lamports>
+  rename <ledger> <name> <new name>
+  faucet <ledger> <authority keypair file> [--new-authority <pubkey>]
+         [--airdrop-limit <lamports>] [--withdraw-limit <lamports>]
+  airdrop <ledger> <authority keypair file> <wallet account> <lamports>
+  withdraw <ledger> <authority keypair file> <wallet keypair file> <lamports>
+  close <ledger> <account keypair file> <destination wallet account>
+  invoke <ledger> <signer keypair file> <program account> [<account>...] [--input <n>[,<n>...]]
+  balance <ledger> <account>
+  list <ledger> [--kind <kind>] [--owner <program account>] [--offset <n>] [--limit <n>]
+  supply <ledger>
+  show <ledger> <account>
+  export <ledger> <output file> [--format tsv|json|csv]
+  import <ledger> <input file> [--format json|csv|solana] [--faucet <authority keypair file>]
+  verify <ledger>
+
+Accounts are given by pubkey or by name, names are case insensitive. The faucet is
+configured before the first account is created, by init or import --faucet or by the
+faucet command, later changes are signed by its authority. Ledgers holding accounts
+without a faucet get one by exporting and importing them with --faucet. Faucet limits are per account and per epoch, airdrops are the only way
+lamports enter the ledger and the --payer wallet funds the accounts it creates. Program data accounts with an --owner hold the state
+of that program, invoke passes the --input numbers to it as little endian u64s.
+Formats default to the file extension, solana reads `solana account --output json`
+dumps and genesis account maps. tsv and csv files hold accounts only, json files also
+hold names, the faucet policy and the fee and rent schedules; import --faucet replaces
+the exported faucet policy. Mutations are recorded in the journal <ledger>.journal, each command that
+changes the ledger produces a block in <ledger>.blocks.";
+
+const EXIT_LEDGER_ERROR: u8 = 1;
+const EXIT_USAGE_ERROR: u8 = 2;
+
+const FORMATS: [&str; 4] = ["tsv", "json", "csv", "solana"];
+
+/// options that do not take a value
+const FLAGS: [&str; 2] = ["--executable", "--help"];
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Ledger(LedgerError),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            Self::Ledger(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<LedgerError> for CliError {
+    fn from(err: LedgerError) -> Self {
+        Self::Ledger(err)
+    }
+}
+
+pub fn run(args: Vec<String>) -> ExitCode {
+    let mut stdout = std::io::stdout();
+    match execute(args, &mut stdout) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            match err {
+                CliError::Usage(_) => ExitCode::from(EXIT_USAGE_ERROR),
+                CliError::Ledger(_) => ExitCode::from(EXIT_LEDGER_ERROR),
+            }
+        }
+    }
+}
+
+pub fn execute(args: Vec<String>, out: &mut impl Write) -> Result<(), CliError> {
+    let args = Args::parse(args)?;
+    if args.has_flag("--help") {
+        return write_out(out, USAGE);
+    }
+
+    let command = args.positional(0, "command")?;
+    let path = args.positional(1, "ledger")?;
+    match command {
+        "init" => init(path, &args, out),
+        "create-account" => create_account(path, &args, out),
+        "transfer" => transfer(path, &args, out),
+        "rename" => rename(path, &args, out),
+        "faucet" => faucet(path, &args, out),
+        "airdrop" => airdrop(path, &args, out),
+        "withdraw" => withdraw(path, &args, out),
+        "close" => close(path, &args, out),
+        "invoke" => invoke(path, &args, out),
+        "balance" => balance(path, &args, out),
+        "list" => list(path, &args, out),
+        "supply" => supply(path, out),
+        "show" => show(path, &args, out),
+        "export" => export(path, &args, out),
+        "import" => import(path, &args, out),
+        "verify" => verify(path, out),
+        _ => Err(CliError::Usage(format!("unknown command {}", command))),
+    }
+}
+
+fn init(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    check_new_ledger(path)?;
+
+    let mut ledger = Ledger::new();
+    ledger.open_journal(&journal_path(path))?;
+    ledger.open_block_store(&blocks_path(path))?;
+    init_faucet(&mut ledger, args)?;
+    commit(&mut ledger, path)?;
+    write_out(out, format!("created ledger {}", path))
+}
+
+fn create_account(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let kind = parse_kind(args.positional(2, "kind")?)?;
+    let account_type = match kind {
+        AccountKind::Wallet => AccountType::Wallet {
+            balance: args.number("--balance")?.unwrap_or(0),
+        },
+        AccountKind::Program => {
+            let program_data = match (
+                args.option("--data"),
+                args.option("--data-file"),
+                args.number("--space")?,
+            ) {
+                (Some(data), None, None) => data.as_bytes().to_vec(),
+                (None, Some(file), None) => fs::read(file)
+                    .map_err(|err| LedgerError::SerializationError(err.to_string()))?,
+                (None, None, Some(space)) => vec![0; space],
+                (None, None, None) => Vec::new(),
+                _ => {
+                    return Err(CliError::Usage(String::from(
+                        "--data, --data-file and --space can not be used together",
+                    )));
+                }
+            };
+            if args.has_flag("--executable") && args.option("--owner").is_some() {
+                return Err(CliError::Usage(String::from(
+                    "--owner only applies to program data accounts",
+                )));
+            }
+            AccountType::Program {
+                executable: args.has_flag("--executable"),
+                program_data,
+            }
+        }
+        AccountKind::TokenAccount => AccountType::TokenAccount {
+            mint: parse_pubkey(args.required("--mint")?)?,
+            token_balance: 0,
+            delegate: args.option("--delegate").map(parse_pubkey).transpose()?,
+        },
+        AccountKind::Stake => AccountType::Stake {
+            validator: parse_pubkey(args.required("--validator")?)?,
+            staked_amount: args.number("--amount")?.unwrap_or(0),
+            activation_epoch: None,
+            deactivation_epoch: None,
+        },
+        AccountKind::Mint => AccountType::Mint {
+            decimals: args.number("--decimals")?.unwrap_or(9),
+            supply: 0,
+            mint_authority: args
+                .option("--mint-authority")
+                .map(parse_pubkey)
+                .transpose()?,
+            freeze_authority: args
+                .option("--freeze-authority")
+                .map(parse_pubkey)
+                .transpose()?,
+        },
+    };
+
+    let mut ledger = open_ledger(path)?;
+    if let Some(name) = args.option("--name") {
+        ledger.names().check_available(name)?;
+    }
+    let payer = args.option("--payer").map(read_keypair).transpose()?;
+    let keypair_path = args.option("--keypair");
+    if let Some(keypair_path) = keypair_path {
+        check_missing(keypair_path)?;
+    }
+    let keypair = Keypair::new();
+    let account = Account::from_keypair(&keypair, account_type).with_rent(&ledger.rent());
+    let account = match args.option("--owner") {
+        Some(owner) => account.owned_by(ledger.resolve(owner)?),
+        None => account,
+    };
+
+    // without a payer the account can only start without lamports
+    let pubkey = account.pubkey;
+    ledger.create_account(payer.as_ref().unwrap_or(&keypair), &keypair, account)?;
+    if let Some(name) = args.option("--name") {
+        ledger.register_name(name, &pubkey)?;
+    }
+    commit(&mut ledger, path)?;
+    // written once the account exists, a failed creation leaves no key behind
+    if let Some(keypair_path) = keypair_path {
+        check_missing(keypair_path)?;
+        write_keypair_file(&keypair, keypair_path)
+            .map_err(|err| LedgerError::SerializationError(format!("{}: {}", keypair_path, err)))?;
+    }
+    write_out(out, pubkey)
+}
+
+fn transfer(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let from = read_keypair(args.positional(2, "from keypair file")?)?;
+    let amount = parse_number(args.positional(4, "lamports")?)?;
+
+    let mut ledger = open_ledger(path)?;
+    let to = ledger.resolve(args.positional(3, "to account")?)?;
+    ledger.transfer(&from, to, amount)?;
+    commit(&mut ledger, path)?;
+    write_out(out, format!("transferred {} lamports to {}", amount, to))
+}
+
+fn faucet(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let signer = read_keypair(args.positional(2, "authority keypair file")?)?;
+    let authority = match args.option("--new-authority") {
+        Some(authority) => parse_pubkey(authority)?,
+        None => signer.pubkey(),
+    };
+    let mut policy = FaucetPolicy::new(authority);
+    if let Some(limit) = args.number("--airdrop-limit")? {
+        policy = policy.with_airdrop_limit(limit);
+    }
+    if let Some(limit) = args.number("--withdraw-limit")? {
+        policy = policy.with_withdraw_limit(limit);
+    }
+
+    let mut ledger = open_ledger(path)?;
+    ledger.set_faucet_policy(&signer, policy)?;
+    commit(&mut ledger, path)?;
+    write_out(out, format!("faucet authority set to {}", authority))
+}
+
+/// Hands the faucet of a ledger without accounts yet to the `--faucet`
+/// authority, if given.
+fn init_faucet(ledger: &mut Ledger, args: &Args) -> Result<(), CliError> {
+    if let Some(authority_path) = args.option("--faucet") {
+        let authority = read_keypair(authority_path)?;
+        ledger.set_faucet_policy(&authority, FaucetPolicy::new(authority.pubkey()))?;
+    }
+
+    Ok(())
+}
+
+fn airdrop(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let authority = read_keypair(args.positional(2, "authority keypair file")?)?;
+    let amount = parse_number(args.positional(4, "lamports")?)?;
+
+    let mut ledger = open_ledger(path)?;
+    let balance = ledger.airdrop(&authority, args.positional(3, "wallet account")?, amount)?;
+    commit(&mut ledger, path)?;
+    write_out(out, balance)
+}
+
+fn withdraw(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let authority = read_keypair(args.positional(2, "authority keypair file")?)?;
+    let from = read_keypair(args.positional(3, "wallet keypair file")?)?;
+    let amount = parse_number(args.positional(4, "lamports")?)?;
+
+    let mut ledger = open_ledger(path)?;
+    let balance = ledger.withdraw(&authority, &from, amount)?;
+    commit(&mut ledger, path)?;
+    write_out(out, balance)
+}
+
+fn close(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let owner = read_keypair(args.positional(2, "account keypair file")?)?;
+    let destination = args.positional(3, "destination wallet account")?;
+
+    let mut ledger = open_ledger(path)?;
+    let closure = ledger.close_account(&owner, destination)?;
+    commit(&mut ledger, path)?;
+    write_out(
+        out,
+        format!(
+            "closed {}, {} lamports moved to {}",
+            closure.pubkey, closure.lamports, closure.destination
+        ),
+    )
+}
+
+fn invoke(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let signer = read_keypair(args.positional(2, "signer keypair file")?)?;
+    let program = args.positional(3, "program account")?;
+    let mut data = Vec::new();
+    for value in args
+        .option("--input")
+        .into_iter()
+        .flat_map(|input| input.split(','))
+    {
+        data.extend(parse_number::<u64>(value.trim())?.to_le_bytes());
+    }
+
+    let mut ledger = open_ledger(path)?;
+    ledger.invoke(&signer, program, &args.positional[4..], data)?;
+    commit(&mut ledger, path)?;
+    write_out(out, format!("invoked {}", program))
+}
+
+fn rename(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let from = args.positional(2, "name")?;
+    let to = args.positional(3, "new name")?;
+
+    let mut ledger = open_ledger(path)?;
+    let pubkey = ledger.rename_account(from, to)?;
+    commit(&mut ledger, path)?;
+    write_out(out, format!("renamed {} to {} for {}", from, to, pubkey))
+}
+
+fn balance(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let ledger = load_ledger(path)?;
+    let account = find_account(&ledger, args.positional(2, "account")?)?;
+    write_out(out, account.lamports)
+}
+
+fn list(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let mut query = AccountQuery::new();
+    if let Some(kind) = args.option("--kind") {
+        query = query.kind(parse_kind(kind)?);
+    }
+    if let Some(owner) = args.option("--owner") {
+        query = query.owner(owner);
+    }
+    if let Some(offset) = args.number("--offset")? {
+        query = query.offset(offset);
+    }
+    if let Some(limit) = args.number("--limit")? {
+        query = query.limit(limit);
+    }
+
+    let ledger = load_ledger(path)?;
+    for account in ledger.query(&query) {
+        write_out(out, account.summary())?;
+    }
+    Ok(())
+}
+
+fn supply(path: &str, out: &mut impl Write) -> Result<(), CliError> {
+    let ledger = load_ledger(path)?;
+    write_out(out, ledger.supply_report().total)
+}
+
+fn show(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let ledger = load_ledger(path)?;
+    let account = find_account(&ledger, args.positional(2, "account")?)?;
+    write_out(
+        out,
+        format!(
+            "pubkey: {}\nnames: {}\nkind: {}\nowner: {}\nlamports: {}\ncreated at: {}\n{:#?}",
+            account.pubkey,
+            ledger
+                .names_of(&account.pubkey)
+                .collect::<Vec<_>>()
+                .join(", "),
+            account.kind(),
+            account.owner(),
+            account.lamports,
+            account.created_at(),
+            account.account_type
+        ),
+    )
+}
+
+fn export(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let output = args.positional(2, "output file")?;
+    let ledger = load_ledger(path)?;
+
+    let contents = match file_format(args, output, "tsv")? {
+        "json" => interchange::ledger_to_json(&ledger)?,
+        "csv" => interchange::ledger_to_csv(&ledger)?,
+        "solana" => {
+            return Err(CliError::Usage(String::from("can not export solana files")));
+        }
+        _ => {
+            let mut contents = String::from("pubkey\tkind\towner\tlamports\tcreated_at\n");
+            for account in ledger.accounts() {
+                contents.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    account.pubkey,
+                    account.kind(),
+                    account.owner(),
+                    account.lamports,
+                    account.created_at()
+                ));
+            }
+            contents
+        }
+    };
+    fs::write(output, contents).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+
+    write_out(
+        out,
+        format!(
+            "exported {} accounts to {}",
+            ledger.accounts().count(),
+            output
+        ),
+    )
+}
+
+fn import(path: &str, args: &Args, out: &mut impl Write) -> Result<(), CliError> {
+    let input = args.positional(2, "input file")?;
+    check_new_ledger(path)?;
+
+    let contents = fs::read_to_string(input)
+        .map_err(|err| LedgerError::SerializationError(format!("{}: {}", input, err)))?;
+    let imported = match file_format(args, input, "json")? {
+        "csv" => interchange::ledger_from_csv(&contents)?,
+        "json" => interchange::ledger_from_json(&contents)?,
+        "solana" => {
+            let import = solana::import_accounts(&contents)?;
+            for pubkey in &import.skipped {
+                write_out(out, format!("skipped {}, no matching account type", pubkey))?;
+            }
+            for mint in &import.missing_mints {
+                write_out(
+                    out,
+                    format!("mint {} of imported token accounts is missing", mint),
+                )?;
+            }
+            Ledger::from_accounts(0, import.accounts)?
+        }
+        format => {
+            return Err(CliError::Usage(format!("can not import {} files", format)));
+        }
+    };
+
+    // everything goes through the journal so the ledger replays from genesis
+    let mut ledger = Ledger::new();
+    ledger.open_journal(&journal_path(path))?;
+    ledger.open_block_store(&blocks_path(path))?;
+    if args.option("--faucet").is_some() {
+        init_faucet(&mut ledger, args)?;
+    } else if imported.faucet().policy.authority.is_some() {
+        ledger.init_faucet_policy(imported.faucet().policy.clone())?;
+    }
+    for account in imported.accounts() {
+        ledger.add_account(account.clone())?;
+    }
+    for (name, pubkey) in imported.names().iter() {
+        ledger.register_name(name, pubkey)?;
+    }
+    for _ in 0..imported.epoch() {
+        ledger.advance_epoch()?;
+    }
+    ledger.set_fees(imported.fees().clone())?;
+    ledger.set_rent(imported.rent())?;
+    commit(&mut ledger, path)?;
+
+    write_out(
+        out,
+        format!(
+            "imported {} accounts into {}",
+            ledger.accounts().count(),
+            path
+        ),
+    )
+}
+
+/// `--format` when given, otherwise the extension of `file`.
+fn file_format<'a>(args: &'a Args, file: &'a str, default: &'a str) -> Result<&'a str, CliError> {
+    let format = args.option("--format").unwrap_or_else(|| {
+        Path::new(file)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .filter(|extension| FORMATS.contains(extension))
+            .unwrap_or(default)
+    });
+    if !FORMATS.contains(&format) {
+        return Err(CliError::Usage(format!("unknown format {}", format)));
+    }
+
+    Ok(format)
+}
+
+fn verify(path: &str, out: &mut impl Write) -> Result<(), CliError> {
+    let mut ledger = load_ledger(path)?;
+    ledger.open_block_store(&blocks_path(path))?;
+    ledger.verify_blocks()?;
+    ledger.verify().into_result()?;
+    write_out(out, format!("verified {} blocks", ledger.blocks().len()))
+}
+
+/// Loads the ledger at `path`, warning about what migrating it dropped.
+fn load_ledger(path: &str) -> Result<Ledger, LedgerError> {
+    let ledger = Ledger::load_ledger(path)?;
+    for warning in ledger.migration_warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    Ok(ledger)
+}
+
+/// Fails when `path` exists, files are never overwritten.
+fn check_missing(path: &str) -> Result<(), LedgerError> {
+    if Path::new(path).exists() {
+        return Err(LedgerError::SerializationError(format!(
+            "{} already exists",
+            path
+        )));
+    }
+
+    Ok(())
+}
+
+/// A new ledger at `path` must not find the journal or blocks of an older
+/// one, it would replay them or chain its blocks onto them.
+fn check_new_ledger(path: &str) -> Result<(), LedgerError> {
+    check_missing(path)?;
+    check_missing(&journal_path(path))?;
+    check_missing(&blocks_path(path))
+}
+
+fn open_ledger(path: &str) -> Result<Ledger, LedgerError> {
+    let mut ledger = load_ledger(path)?;
+    ledger.open_journal(&journal_path(path))?;
+    ledger.open_block_store(&blocks_path(path))?;
+    Ok(ledger)
+}
+
+/// Closes the slot of the command and saves the ledger in the state the
+/// new block records.
+fn commit(ledger: &mut Ledger, path: &str) -> Result<(), LedgerError> {
+    ledger.produce_block()?;
+    ledger.save_ledger(path)
+}
+
+fn journal_path(path: &str) -> String {
+    format!("{}.journal", path)
+}
+
+fn blocks_path(path: &str) -> String {
+    format!("{}.blocks", path)
+}
+
+fn find_account<'a>(ledger: &'a Ledger, account: &str) -> Result<&'a Account, LedgerError> {
+    ledger
+        .find_account(account)
+        .ok_or_else(|| LedgerError::AccountNotFound(account.to_string()))
+}
+
+fn read_keypair(path: &str) -> Result<Keypair, LedgerError> {
+    read_keypair_file(path)
+        .map_err(|err| LedgerError::SerializationError(format!("{}: {}", path, err)))
+}
+
+fn write_out(out: &mut impl Write, line: impl Display) -> Result<(), CliError> {
+    writeln!(out, "{}", line).map_err(|err| LedgerError::SerializationError(err.to_string()).into())
+}
+
+fn parse_kind(value: &str) -> Result<AccountKind, CliError> {
+    value
+        .parse()
+        .map_err(|err: LedgerError| CliError::Usage(err.to_string()))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("{} is not a valid number", value)))
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    fn parse(args: Vec<String>) -> Result<Self, CliError> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                positional.push(arg);
+            } else if FLAGS.contains(&arg.as_str()) {
+                options.insert(arg, None);
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{} requires a value", arg)))?;
+                options.insert(arg, Some(value));
+            }
+        }
+
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn positional(&self, index: usize, name: &str) -> Result<&str, CliError> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| CliError::Usage(format!("missing <{}>", name)))
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.options.contains_key(flag)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|value| value.as_deref())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.option(name)
+            .ok_or_else(|| CliError::Usage(format!("missing {}", name)))
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        self.option(name).map(parse_number).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::vm::op;
+    use solana_sdk::signature::Signer;
+
+    /// Runs the command line `args` and returns its trimmed output.
+    fn run(args: &[&str]) -> Result<String, CliError> {
+        let mut out = Vec::new();
+        execute(args.iter().map(|arg| arg.to_string()).collect(), &mut out)
+            .map(|_| String::from_utf8(out).unwrap().trim().to_string())
+    }
+
+    #[test]
+    fn test_cli_ledger_operations() {
+        let dir = std::env::temp_dir().join(format!("ledger-cli-{}", Keypair::new().pubkey()));
+        let ledger_path = dir.join("ledger.bin").to_string_lossy().to_string();
+        let keypair_path = dir.join("wallet.json").to_string_lossy().to_string();
+        let authority_path = dir.join("authority.json").to_string_lossy().to_string();
+
+        run(&["init", &ledger_path]).unwrap();
+        assert!(run(&["init", &ledger_path]).is_err());
+        // a new ledger does not replay the journal left by a removed one
+        let stale_path = dir.join("stale.bin").to_string_lossy().to_string();
+        run(&["init", &stale_path]).unwrap();
+        fs::remove_file(&stale_path).unwrap();
+        assert!(run(&["init", &stale_path]).is_err());
+        fs::remove_file(journal_path(&stale_path)).unwrap();
+        assert!(run(&["init", &stale_path]).is_err());
+        fs::remove_file(blocks_path(&stale_path)).unwrap();
+        run(&["init", &stale_path]).unwrap();
+        write_keypair_file(&Keypair::new(), &authority_path).unwrap();
+        run(&["faucet", &ledger_path, &authority_path]).unwrap();
+
+        let from = run(&[
+            "create-account",
+            &ledger_path,
+            "wallet",
+            "--keypair",
+            &keypair_path,
+        ])
+        .unwrap();
+        assert_eq!(
+            run(&["airdrop", &ledger_path, &authority_path, &from, "550"]).unwrap(),
+            "550"
+        );
+        // lamports of new accounts come from a payer
+        let savings = ["create-account", &ledger_path, "wallet", "--balance", "50"];
+        let err = run(&savings).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidTransfer(_))
+        ));
+        // the keypair file is only written for accounts that were created
+        let orphan_path = dir.join("orphan.json").to_string_lossy().to_string();
+        let err = run(&[&savings[..], &["--keypair", &orphan_path]].concat()).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidTransfer(_))
+        ));
+        assert!(!Path::new(&orphan_path).exists());
+        let err = run(&[
+            "create-account",
+            &ledger_path,
+            "wallet",
+            "--keypair",
+            &keypair_path,
+        ])
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::SerializationError(_))
+        ));
+        let to = run(&[
+            &savings[..],
+            &["--payer", &keypair_path, "--name", "Savings"],
+        ]
+        .concat())
+        .unwrap();
+        run(&["transfer", &ledger_path, &keypair_path, "savings", "150"]).unwrap();
+        let err = run(&["deposit", &ledger_path, &to, "50"]).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+
+        assert_eq!(run(&["balance", &ledger_path, &from]).unwrap(), "350");
+        assert_eq!(run(&["balance", &ledger_path, &to]).unwrap(), "200");
+        assert_eq!(run(&["balance", &ledger_path, "SAVINGS"]).unwrap(), "200");
+        assert_eq!(run(&["supply", &ledger_path]).unwrap(), "550");
+        assert_eq!(
+            run(&["list", &ledger_path, "--kind", "wallet"])
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+
+        let err = run(&["list", &ledger_path, "--kind", "toke_account"]).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+        let err = run(&["transfer", &ledger_path, &keypair_path, &to]).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+        let unknown = Keypair::new().pubkey().to_string();
+        let err = run(&["balance", &ledger_path, &unknown]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::AccountNotFound(_))
+        ));
+        let err = run(&["balance", &ledger_path, "unknown"]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::AccountNotFound(_))
+        ));
+        let err = run(&[
+            "create-account",
+            &ledger_path,
+            "wallet",
+            "--name",
+            "savings",
+        ])
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::NameTaken { .. })
+        ));
+        run(&["rename", &ledger_path, "savings", "Reserve"]).unwrap();
+        assert_eq!(run(&["balance", &ledger_path, "reserve"]).unwrap(), "200");
+
+        let replayed = Ledger::replay(&journal_path(&ledger_path)).unwrap();
+        assert_eq!(replayed.total_supply().unwrap(), 550);
+        assert!(replayed.account_by_name("reserve").is_some());
+
+        assert_eq!(run(&["verify", &ledger_path]).unwrap(), "verified 7 blocks");
+
+        let json_path = dir.join("accounts.json").to_string_lossy().to_string();
+        let imported_path = dir.join("imported.bin").to_string_lossy().to_string();
+        run(&["export", &ledger_path, &json_path]).unwrap();
+        run(&["import", &imported_path, &json_path]).unwrap();
+        assert_eq!(run(&["balance", &imported_path, &from]).unwrap(), "350");
+        // names and the faucet come along with a json export
+        assert_eq!(run(&["balance", &imported_path, "reserve"]).unwrap(), "200");
+        assert_eq!(
+            run(&["airdrop", &imported_path, &authority_path, &from, "25"]).unwrap(),
+            "375"
+        );
+
+        let csv_path = dir.join("accounts.csv").to_string_lossy().to_string();
+        let unfunded_path = dir.join("unfunded.bin").to_string_lossy().to_string();
+        run(&["export", &ledger_path, &csv_path]).unwrap();
+        run(&["import", &unfunded_path, &csv_path]).unwrap();
+        let err = run(&["faucet", &unfunded_path, &authority_path]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidAuthority(_))
+        ));
+        // the imported ledger is funded by the faucet it was imported with
+        let funded_path = dir.join("funded.bin").to_string_lossy().to_string();
+        run(&[
+            "import",
+            &funded_path,
+            &csv_path,
+            "--faucet",
+            &authority_path,
+        ])
+        .unwrap();
+        run(&[
+            "faucet",
+            &funded_path,
+            &authority_path,
+            "--airdrop-limit",
+            "1000",
+        ])
+        .unwrap();
+        assert_eq!(
+            run(&["airdrop", &funded_path, &authority_path, &from, "25"]).unwrap(),
+            "375"
+        );
+        let err = run(&["export", &ledger_path, &json_path, "--format", "xml"]).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+
+        let mut ledger = Ledger::load_ledger(&ledger_path).unwrap();
+        ledger
+            .transfer(
+                &read_keypair_file(&keypair_path).unwrap(),
+                parse_pubkey(&to).unwrap(),
+                1,
+            )
+            .unwrap();
+        ledger.save_ledger(&ledger_path).unwrap();
+        let err = run(&["verify", &ledger_path]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidBlock { slot: 6, .. })
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cli_supply_operations() {
+        let dir = std::env::temp_dir().join(format!("ledger-cli-{}", Keypair::new().pubkey()));
+        let ledger_path = dir.join("ledger.bin").to_string_lossy().to_string();
+        let wallet_path = dir.join("wallet.json").to_string_lossy().to_string();
+        let authority_path = dir.join("authority.json").to_string_lossy().to_string();
+        let scratch_path = dir.join("scratch.json").to_string_lossy().to_string();
+
+        run(&["init", &ledger_path]).unwrap();
+        let authority = Keypair::new();
+        write_keypair_file(&authority, &authority_path).unwrap();
+        run(&[
+            "faucet",
+            &ledger_path,
+            &authority_path,
+            "--airdrop-limit",
+            "100",
+        ])
+        .unwrap();
+        run(&[
+            "create-account",
+            &ledger_path,
+            "wallet",
+            "--name",
+            "tester",
+            "--keypair",
+            &wallet_path,
+        ])
+        .unwrap();
+
+        let err = run(&["airdrop", &ledger_path, &wallet_path, "tester", "10"]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidAuthority(_))
+        ));
+        // only the authority changes the policy once accounts exist
+        let err = run(&["faucet", &ledger_path, &wallet_path]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidAuthority(_))
+        ));
+        assert_eq!(
+            run(&["airdrop", &ledger_path, &authority_path, "tester", "70"]).unwrap(),
+            "70"
+        );
+        let err = run(&["airdrop", &ledger_path, &authority_path, "tester", "70"]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::RateLimitExceeded { .. })
+        ));
+        assert_eq!(
+            run(&[
+                "withdraw",
+                &ledger_path,
+                &authority_path,
+                &wallet_path,
+                "20"
+            ])
+            .unwrap(),
+            "50"
+        );
+        assert_eq!(run(&["supply", &ledger_path]).unwrap(), "50");
+
+        let scratch = run(&[
+            "create-account",
+            &ledger_path,
+            "wallet",
+            "--name",
+            "scratch",
+            "--keypair",
+            &scratch_path,
+            "--balance",
+            "5",
+            "--payer",
+            &wallet_path,
+        ])
+        .unwrap();
+        assert_eq!(run(&["balance", &ledger_path, "tester"]).unwrap(), "45");
+        let err = run(&["close", &ledger_path, &wallet_path, "tester"]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::InvalidTransfer(_))
+        ));
+        run(&["close", &ledger_path, &scratch_path, "tester"]).unwrap();
+        assert_eq!(run(&["balance", &ledger_path, "tester"]).unwrap(), "50");
+        let err = run(&["balance", &ledger_path, &scratch]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::AccountNotFound(_))
+        ));
+        // the ledger charges no rent, a mint is created without a payer
+        let mint = run(&["create-account", &ledger_path, "mint"]).unwrap();
+        assert_eq!(run(&["balance", &ledger_path, &mint]).unwrap(), "0");
+        assert_eq!(run(&["verify", &ledger_path]).unwrap(), "verified 8 blocks");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cli_invoke() {
+        let dir = std::env::temp_dir().join(format!("ledger-cli-{}", Keypair::new().pubkey()));
+        let ledger_path = dir.join("ledger.bin").to_string_lossy().to_string();
+        let keypair_path = dir.join("signer.json").to_string_lossy().to_string();
+        let code_path = dir.join("counter.bin").to_string_lossy().to_string();
+        let payer_path = dir.join("payer.json").to_string_lossy().to_string();
+
+        write_keypair_file(&Keypair::new(), &keypair_path).unwrap();
+        run(&["init", &ledger_path, "--faucet", &keypair_path]).unwrap();
+        let payer = run(&[
+            "create-account",
+            &ledger_path,
+            "wallet",
+            "--keypair",
+            &payer_path,
+        ])
+        .unwrap();
+        run(&["airdrop", &ledger_path, &keypair_path, &payer, "100000000"]).unwrap();
+        // adds the first input number to the counter in account 0
+        let push = |value: u64| [&[op::PUSH][..], &value.to_le_bytes()].concat();
+        let code = [
+            push(0),
+            push(0),
+            push(0),
+            push(0),
+            vec![op::LOAD],
+            push(0),
+            vec![op::INPUT, op::ADD, op::STORE],
+        ]
+        .concat();
+        fs::write(&code_path, code).unwrap();
+
+        let program = ["--data-file", &code_path, "--executable"];
+        run(&[
+            &[
+                "create-account",
+                &ledger_path,
+                "program",
+                "--name",
+                "counter",
+                "--payer",
+                &payer_path,
+            ],
+            &program[..],
+        ]
+        .concat())
+        .unwrap();
+        let state = [
+            "create-account",
+            &ledger_path,
+            "program",
+            "--space",
+            "8",
+            "--payer",
+            &payer_path,
+        ];
+        run(&[&state[..], &["--owner", "counter", "--name", "tally"]].concat()).unwrap();
+        run(&[&state[..], &["--name", "unowned"]].concat()).unwrap();
+
+        for input in ["5", "2,9"] {
+            assert_eq!(
+                run(&[
+                    "invoke",
+                    &ledger_path,
+                    &keypair_path,
+                    "counter",
+                    "tally",
+                    "--input",
+                    input
+                ])
+                .unwrap(),
+                "invoked counter"
+            );
+        }
+        let ledger = Ledger::load_ledger(&ledger_path).unwrap();
+        assert!(matches!(
+            &find_account(&ledger, "tally").unwrap().account_type,
+            AccountType::Program { program_data, .. } if program_data[..] == 7u64.to_le_bytes()
+        ));
+
+        let err = run(&["invoke", &ledger_path, &keypair_path, "counter", "unowned"]).unwrap_err();
+        assert!(matches!(
+            err,
+            CliError::Ledger(LedgerError::ProgramFailed { .. })
+        ));
+        let err = run(&[&state[..], &["--owner", "counter", "--executable"]].concat()).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+        assert_eq!(run(&["verify", &ledger_path]).unwrap(), "verified 8 blocks");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,22 @@
 pub mod account;
+pub mod block;
 pub mod errors;
+pub mod faucet;
+pub mod fee;
+pub mod format;
+pub mod interchange;
+pub mod journal;
 pub mod ledger;
-pub mod lib;
+pub mod merkle;
+pub mod names;
+pub mod processor;
+pub mod query;
+pub mod rent;
+pub mod shared;
+pub mod solana;
+pub mod stake;
+pub mod storage;
+pub mod supply;
+pub mod transaction;
+pub mod verify;
+pub mod vm;
@@ -1,55 +1,89 @@
 use borsh::{BorshDeserialize, BorshSerialize, to_vec};
-use solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
 use std::{
     fmt::Display,
     mem,
+    str::FromStr,
     time::{SystemTime, UNIX_EPOCH},
-    u64,
 };
 
-use crate::pkg::errors::LedgerError;
+use crate::pkg::{
+    errors::LedgerError,
+    interchange::{base64_bytes, option_pubkey_str, pubkey_str},
+    rent::Rent,
+};
+
+/// Programs owning the accounts of each type.
+pub const SYSTEM_PROGRAM_ID: Pubkey = pubkey!("11111111111111111111111111111111");
+pub const BPF_LOADER_ID: Pubkey = pubkey!("BPFLoader2111111111111111111111111111111111");
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const STAKE_PROGRAM_ID: Pubkey = pubkey!("Stake11111111111111111111111111111111111111");
 
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum AccountType {
     Wallet {
         balance: u64,
     },
     Program {
         executable: bool,
+        #[serde(with = "base64_bytes")]
         program_data: Vec<u8>,
     },
     TokenAccount {
-        mint: String,
+        #[serde(with = "pubkey_str")]
+        mint: Pubkey,
         token_balance: u64,
-        delegate: Option<String>,
+        #[serde(default, with = "option_pubkey_str")]
+        delegate: Option<Pubkey>,
     },
     Stake {
-        validator: String,
+        #[serde(with = "pubkey_str")]
+        validator: Pubkey,
         staked_amount: u64,
+        activation_epoch: Option<u64>,
+        deactivation_epoch: Option<u64>,
+    },
+    Mint {
+        decimals: u8,
+        supply: u64,
+        #[serde(default, with = "option_pubkey_str")]
+        mint_authority: Option<Pubkey>,
+        #[serde(default, with = "option_pubkey_str")]
+        freeze_authority: Option<Pubkey>,
     },
 }
 
 impl AccountType {
-    fn owner(&self) -> String {
+    /// owner program of the accounts holding this type
+    pub fn owner(&self) -> Pubkey {
         match self {
-            AccountType::Wallet { balance: _ } => String::from("system"),
+            AccountType::Wallet { balance: _ } => SYSTEM_PROGRAM_ID,
             AccountType::Stake {
                 validator: _,
                 staked_amount: _,
-            } => String::from("system"),
+                activation_epoch: _,
+                deactivation_epoch: _,
+            } => STAKE_PROGRAM_ID,
             AccountType::Program {
                 executable: _,
                 program_data: _,
-            } => String::from("BPFLoader"),
+            } => BPF_LOADER_ID,
             AccountType::TokenAccount {
                 mint: _,
                 token_balance: _,
                 delegate: _,
-            } => String::from("Token"),
+            } => TOKEN_PROGRAM_ID,
+            AccountType::Mint { .. } => TOKEN_PROGRAM_ID,
         }
     }
 
-    fn balance(&self) -> u64 {
+    fn balance(&self, rent: &Rent) -> u64 {
         match self {
             Self::Wallet { balance } => *balance,
             Self::TokenAccount {
@@ -60,8 +94,26 @@ impl AccountType {
             Self::Stake {
                 validator: _,
                 staked_amount,
+                activation_epoch: _,
+                deactivation_epoch: _,
             } => *staked_amount,
-            _ => 1,
+            // programs and mints start funded to be rent exempt
+            _ => rent.minimum_balance(self.data_len()),
+        }
+    }
+
+    /// Size of the account data, what rent is charged for.
+    pub fn data_len(&self) -> usize {
+        to_vec(self).map(|buff| buff.len()).unwrap_or_default()
+    }
+
+    pub fn kind(&self) -> AccountKind {
+        match self {
+            AccountType::Wallet { .. } => AccountKind::Wallet,
+            AccountType::Program { .. } => AccountKind::Program,
+            AccountType::TokenAccount { .. } => AccountKind::TokenAccount,
+            AccountType::Stake { .. } => AccountKind::Stake,
+            AccountType::Mint { .. } => AccountKind::Mint,
         }
     }
 }
@@ -82,29 +134,91 @@ impl Display for AccountType {
             AccountType::Stake {
                 validator: _,
                 staked_amount: _,
+                activation_epoch: _,
+                deactivation_epoch: _,
             } => write!(f, "Stake"),
+            AccountType::Mint { .. } => write!(f, "Mint"),
         }
     }
 }
 
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+/// The variant of an `AccountType` without its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AccountKind {
+    Wallet,
+    Program,
+    TokenAccount,
+    Stake,
+    Mint,
+}
+
+impl AccountKind {
+    pub const ALL: [AccountKind; 5] = [
+        AccountKind::Wallet,
+        AccountKind::Program,
+        AccountKind::TokenAccount,
+        AccountKind::Stake,
+        AccountKind::Mint,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccountKind::Wallet => "wallet",
+            AccountKind::Program => "program",
+            AccountKind::TokenAccount => "token_account",
+            AccountKind::Stake => "stake",
+            AccountKind::Mint => "mint",
+        }
+    }
+}
+
+impl FromStr for AccountKind {
+    type Err = LedgerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AccountKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| LedgerError::InvalidAccountKind(s.to_string()))
+    }
+}
+
+impl Display for AccountKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 pub struct Account {
-    pub pubkey: String,
-    owner: String,
+    #[serde(with = "pubkey_str")]
+    pub pubkey: Pubkey,
+    #[serde(with = "pubkey_str")]
+    owner: Pubkey,
     pub lamports: u64,
     pub account_type: AccountType,
     created_at: u64,
 }
 
 impl Account {
+    /// Creates an account under a random key nobody holds the private half of,
+    /// it can receive funds but never sign for a transaction.
     pub fn new(account_type: AccountType) -> Self {
-        let pubkey = Pubkey::new_unique().to_string();
+        Self::with_pubkey(Pubkey::new_unique(), account_type)
+    }
+
+    /// Creates an account owned by `keypair`, which signs the transactions
+    /// that move funds out of it.
+    pub fn from_keypair(keypair: &Keypair, account_type: AccountType) -> Self {
+        Self::with_pubkey(keypair.pubkey(), account_type)
+    }
 
+    pub fn with_pubkey(pubkey: Pubkey, account_type: AccountType) -> Self {
         Self {
-            pubkey: pubkey,
+            pubkey,
             owner: account_type.owner(),
-            lamports: account_type.balance(),
-            account_type: account_type,
+            lamports: account_type.balance(&Rent::default()),
+            account_type,
             created_at: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .expect("SystemTime set to a time before UNIX EPOCH")
@@ -112,6 +226,59 @@ impl Account {
         }
     }
 
+    /// Funds a program or mint with the exemption minimum of `rent`, they
+    /// start with the mainnet minimum otherwise.
+    pub fn with_rent(mut self, rent: &Rent) -> Self {
+        self.lamports = self.account_type.balance(rent);
+        self
+    }
+
+    /// Hands the account to the `owner` program, which alone may debit it
+    /// and write its data. Only program data accounts change owner.
+    pub fn owned_by(mut self, owner: Pubkey) -> Self {
+        if let AccountType::Program {
+            executable: false, ..
+        } = self.account_type
+        {
+            self.owner = owner;
+        }
+        self
+    }
+
+    /// Rebuilds an account from stored fields, used when migrating older
+    /// file layouts.
+    pub fn from_parts(
+        pubkey: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        account_type: AccountType,
+        created_at: u64,
+    ) -> Self {
+        Self {
+            pubkey,
+            owner,
+            lamports,
+            account_type,
+            created_at,
+        }
+    }
+
+    pub fn kind(&self) -> AccountKind {
+        self.account_type.kind()
+    }
+
+    pub fn data_len(&self) -> usize {
+        self.account_type.data_len()
+    }
+
+    pub fn owner(&self) -> &Pubkey {
+        &self.owner
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
     pub fn is_account_type(&self, account_type: AccountType) -> bool {
         mem::discriminant(&self.account_type) == mem::discriminant(&account_type)
     }
@@ -126,7 +293,7 @@ impl Account {
     }
 
     pub fn from_bytes(buff: &[u8]) -> Result<Account, LedgerError> {
-        let account = Account::try_from_slice(&buff);
+        let account = Account::try_from_slice(buff);
         if let Err(err) = &account {
             return Err(LedgerError::SerializationError(err.to_string()));
         }
@@ -135,22 +302,19 @@ impl Account {
     }
 }
 
+pub fn parse_pubkey(pubkey: &str) -> Result<Pubkey, LedgerError> {
+    Pubkey::from_str(pubkey).map_err(|_| LedgerError::InvalidPubkey(pubkey.to_string()))
+}
+
 pub trait Summarizable {
     fn summary(&self) -> String;
 }
 
 impl Summarizable for Account {
     fn summary(&self) -> String {
-        let clone_key = self.pubkey.clone();
-
-        let summarized_key = clone_key.get(..8).unwrap().to_owned()
-            + ".."
-            + clone_key
-                .chars()
-                .rev()
-                .collect::<String>()
-                .get(..4)
-                .unwrap();
+        let key = self.pubkey.to_string();
+        // base58 keys are ascii, never shorter than 32 characters
+        let summarized_key = format!("{}..{}", &key[..8], &key[key.len() - 4..]);
         let account_type = &self.account_type;
         let sol = (self.lamports as f64) / 1_000_000_000.0;
 
@@ -172,16 +336,25 @@ mod tests {
         };
         assert_eq!(program_type.to_string(), "Program");
         let token_type = AccountType::TokenAccount {
-            mint: "".to_string(),
+            mint: Pubkey::default(),
             token_balance: 0,
             delegate: None,
         };
         assert_eq!(token_type.to_string(), "Token Account");
         let stake_type = AccountType::Stake {
-            validator: "".to_string(),
+            validator: Pubkey::default(),
             staked_amount: 0,
+            activation_epoch: None,
+            deactivation_epoch: None,
         };
         assert_eq!(stake_type.to_string(), "Stake");
+        let mint_type = AccountType::Mint {
+            decimals: 9,
+            supply: 0,
+            mint_authority: None,
+            freeze_authority: None,
+        };
+        assert_eq!(mint_type.to_string(), "Mint");
     }
 
     #[test]
@@ -231,10 +404,10 @@ mod tests {
 
     #[test]
     fn test_account_token_round_trip_serialization() {
-        let mint_data = Pubkey::new_unique().to_string();
+        let mint_data = Pubkey::new_unique();
         let token_balance_data = 200_000_000_000_000;
         let token_account = Account::new(AccountType::TokenAccount {
-            mint: mint_data.clone(),
+            mint: mint_data,
             token_balance: token_balance_data,
             delegate: None,
         });
@@ -263,11 +436,13 @@ mod tests {
 
     #[test]
     fn test_account_stake_round_trip_serialization() {
-        let validator_data = Pubkey::new_unique().to_string();
+        let validator_data = Pubkey::new_unique();
         let staked_amount_data = 2_000_000_000;
         let stake_account = Account::new(AccountType::Stake {
-            validator: validator_data.clone(),
+            validator: validator_data,
             staked_amount: staked_amount_data,
+            activation_epoch: None,
+            deactivation_epoch: None,
         });
 
         let clone_stake_account = serialized_deserialize(stake_account.clone());
@@ -279,6 +454,7 @@ mod tests {
         if let AccountType::Stake {
             validator,
             staked_amount,
+            ..
         } = clone_stake_account.account_type
         {
             assert_eq!(validator_data, validator);
@@ -302,15 +478,19 @@ mod tests {
     #[test]
     fn test_is_account_type() {
         let account = Account::new(AccountType::Stake {
-            validator: String::new(),
+            validator: Pubkey::default(),
             staked_amount: 0,
+            activation_epoch: None,
+            deactivation_epoch: None,
         });
         assert!(account.is_account_type(AccountType::Stake {
-            validator: String::new(),
-            staked_amount: 0
+            validator: Pubkey::default(),
+            staked_amount: 0,
+            activation_epoch: None,
+            deactivation_epoch: None,
         }));
         assert!(!account.is_account_type(AccountType::TokenAccount {
-            mint: String::new(),
+            mint: Pubkey::default(),
             token_balance: 0,
             delegate: None
         }));
@@ -320,25 +500,42 @@ mod tests {
     fn test_account_summary() {
         let lamports: u64 = 20_000_000_000;
         let acc_type = AccountType::Stake {
-            validator: String::new(),
+            validator: Pubkey::default(),
             staked_amount: lamports,
+            activation_epoch: None,
+            deactivation_epoch: None,
         };
         let account = Account::new(acc_type);
 
-        let pubkey = account.pubkey.clone();
-        let sumary_key = pubkey.get(..8).unwrap().to_owned()
-            + ".."
-            + pubkey.chars().rev().collect::<String>().get(..4).unwrap();
+        let pubkey = account.pubkey.to_string();
+        let sumary_key = format!("{}..{}", &pubkey[..8], &pubkey[pubkey.len() - 4..]);
         let sol = (lamports as f64) / 1_000_000_000.0;
         let acc_type_str = AccountType::Stake {
-            validator: String::new(),
+            validator: Pubkey::default(),
             staked_amount: 0,
+            activation_epoch: None,
+            deactivation_epoch: None,
         }
         .to_string();
         assert_eq!(
             account.summary(),
-            format!("{sumary_key}|{acc_type_str}|{sol} SOL")
+            format!("{sumary_key} | {acc_type_str} | {sol} SOL")
         );
+
+        let default_key =
+            Account::with_pubkey(Pubkey::default(), AccountType::Wallet { balance: 0 });
+        assert_eq!(default_key.summary(), "11111111..1111 | Wallet | 0 SOL");
+    }
+
+    #[test]
+    fn test_account_kind_from_str() {
+        for kind in AccountKind::ALL {
+            assert_eq!(kind.name().parse::<AccountKind>().unwrap(), kind);
+        }
+
+        let err = "toke_account".parse::<AccountKind>().unwrap_err();
+        let expected_err = LedgerError::InvalidAccountKind("toke_account".to_string());
+        assert_eq!(err.to_string(), expected_err.to_string());
     }
 
     fn serialized_deserialize(acc: Account) -> Account {
@@ -0,0 +1,203 @@
+use borsh::{BorshDeserialize, BorshSerialize, to_vec};
+use solana_sdk::hash::{Hash, hashv};
+use std::{fs::File, path::Path};
+
+use crate::pkg::{
+    errors::LedgerError,
+    journal::{JournalEntry, append_record, open_append, read_records},
+    merkle::{hash_leaf, merkle_root},
+};
+
+/// Changes applied during one slot, chained to the previous block by its
+/// hash. Blocks hold every journaled change, signed transactions as well as
+/// accounts added at setup, airdrops, withdrawals, names and fee, rent or
+/// epoch changes, so replaying them from the first block rebuilds the state
+/// of each one.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Block {
+    pub slot: u64,
+    pub parent_hash: Hash,
+    /// Merkle root of the block entries
+    pub entries_root: Hash,
+    /// ledger state root once the entries were applied
+    pub state_hash: Hash,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Block {
+    /// Builds the block following `parent`, the first block has slot `0` and
+    /// the default hash as parent.
+    pub fn new(
+        parent: Option<&Block>,
+        entries: Vec<JournalEntry>,
+        state_hash: Hash,
+    ) -> Result<Self, LedgerError> {
+        Ok(Self {
+            slot: parent.map_or(0, |block| block.slot + 1),
+            parent_hash: parent.map(Block::hash).unwrap_or_default(),
+            entries_root: entries_root(&entries)?,
+            state_hash,
+            entries,
+        })
+    }
+
+    /// Hash of the block header, the entries are covered by their root.
+    pub fn hash(&self) -> Hash {
+        hashv(&[
+            &self.slot.to_le_bytes(),
+            self.parent_hash.as_ref(),
+            self.entries_root.as_ref(),
+            self.state_hash.as_ref(),
+        ])
+    }
+}
+
+pub fn entries_root(entries: &[JournalEntry]) -> Result<Hash, LedgerError> {
+    let mut leaves = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let buff = to_vec(entry).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+        leaves.push(hash_leaf(&buff));
+    }
+
+    Ok(merkle_root(&leaves))
+}
+
+/// Chain of produced blocks, appended to a file next to the ledger when one
+/// is open.
+#[derive(Debug, Default)]
+pub struct BlockStore {
+    blocks: Vec<Block>,
+    file: Option<File>,
+}
+
+impl BlockStore {
+    /// Loads the blocks already stored at `path` and appends the next ones
+    /// to it.
+    pub fn open(path: &str) -> Result<Self, LedgerError> {
+        let blocks = if Path::new(path).exists() {
+            read_records(path)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            blocks,
+            file: Some(open_append(path)?),
+        })
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn last(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    pub fn append(&mut self, block: Block) -> Result<&Block, LedgerError> {
+        if let Some(file) = &self.file {
+            append_record(file, &block)?;
+        }
+        self.blocks.push(block);
+
+        Ok(&self.blocks[self.blocks.len() - 1])
+    }
+
+    /// Checks block by block that slots follow each other, that every block
+    /// links to the hash of its parent, and that entries, their roots and
+    /// transaction signatures match. The last block must hold `state_hash`,
+    /// `Ledger::verify_blocks` also replays the entries to check the state
+    /// hash of every block.
+    pub fn verify(&self, state_hash: Hash) -> Result<(), LedgerError> {
+        let mut parent: Option<&Block> = None;
+        for block in &self.blocks {
+            let invalid = |reason: &str| LedgerError::InvalidBlock {
+                slot: block.slot,
+                reason: reason.to_string(),
+            };
+
+            if block.slot != parent.map_or(0, |parent| parent.slot + 1) {
+                return Err(invalid("slot does not follow its parent"));
+            }
+            if block.parent_hash != parent.map(Block::hash).unwrap_or_default() {
+                return Err(invalid("parent hash mismatch"));
+            }
+            if block.entries_root != entries_root(&block.entries)? {
+                return Err(invalid("entries root mismatch"));
+            }
+            for entry in &block.entries {
+                if let JournalEntry::Transaction(tx) = entry {
+                    tx.verify_signatures()
+                        .map_err(|err| invalid(&err.to_string()))?;
+                }
+            }
+            parent = Some(block);
+        }
+
+        if let Some(last) = parent
+            && last.state_hash != state_hash
+        {
+            return Err(LedgerError::InvalidBlock {
+                slot: last.slot,
+                reason: String::from("ledger state does not match the last block"),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::transaction::{Instruction, Transaction};
+    use solana_sdk::{
+        hash::hash,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    };
+
+    #[test]
+    fn test_block_store_verify() {
+        let keypair = Keypair::new();
+        let mut tx = Transaction::new(vec![Instruction::Transfer {
+            from: keypair.pubkey(),
+            to: Pubkey::new_unique(),
+            amount: 1,
+        }]);
+        tx.sign(&keypair).unwrap();
+
+        let mut store = BlockStore::default();
+        let genesis = Block::new(None, vec![], hash(b"genesis")).unwrap();
+        store.append(genesis).unwrap();
+        let block = Block::new(
+            store.last(),
+            vec![JournalEntry::Transaction(tx.clone())],
+            hash(b"state"),
+        )
+        .unwrap();
+        assert_eq!(block.slot, 1);
+        assert_eq!(block.parent_hash, store.blocks()[0].hash());
+        store.append(block).unwrap();
+
+        store.verify(hash(b"state")).unwrap();
+        let err = store.verify(hash(b"other state")).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidBlock { slot: 1, .. }));
+
+        // a transaction swapped after the block was produced
+        let mut tampered = store.blocks()[1].clone();
+        if let JournalEntry::Transaction(tx) = &mut tampered.entries[0] {
+            tx.priority_fee = 5;
+        }
+        let mut forged = BlockStore::default();
+        forged.append(store.blocks()[0].clone()).unwrap();
+        forged.append(tampered).unwrap();
+        let err = forged.verify(hash(b"state")).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidBlock { slot: 1, .. }));
+
+        let mut orphan = BlockStore::default();
+        orphan.append(store.blocks()[1].clone()).unwrap();
+        let err = orphan.verify(hash(b"state")).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidBlock { slot: 1, .. }));
+    }
+}
@@ -3,10 +3,66 @@ use std::{error::Error, fmt::Display};
 #[derive(Debug)]
 pub enum LedgerError {
     AccountNotFound(String),
-    InsufficientFunds { require: u64, available: u64 },
+    InsufficientFunds {
+        require: u64,
+        available: u64,
+    },
     DuplicateAccount(String),
     InvalidTransfer(String),
     SerializationError(String),
+    MissingSignature(String),
+    InvalidSignature(String),
+    InvalidPubkey(String),
+    InvalidAccountKind(String),
+    InvalidAuthority(String),
+    StakeNotDelegated(String),
+    StakeAlreadyDeactivated(String),
+    StakeStillActive {
+        pubkey: String,
+        status: String,
+    },
+    InvalidLedgerFile(String),
+    RentNotExempt {
+        pubkey: String,
+        minimum: u64,
+        lamports: u64,
+    },
+    InvalidBlock {
+        slot: u64,
+        reason: String,
+    },
+    InvalidRecord {
+        location: String,
+        reason: String,
+    },
+    ArithmeticOverflow,
+    InvariantViolation(String),
+    InvalidName(String),
+    NameTaken {
+        name: String,
+        pubkey: String,
+    },
+    RateLimitExceeded {
+        pubkey: String,
+        remaining: u64,
+        requested: u64,
+    },
+    AccountNotEmpty {
+        pubkey: String,
+        reason: String,
+    },
+    InvalidNewAccount {
+        pubkey: String,
+        reason: String,
+    },
+    ProgramFailed {
+        program: String,
+        reason: String,
+    },
+    ComputeBudgetExceeded {
+        program: String,
+        budget: u64,
+    },
 }
 
 impl Display for LedgerError {
@@ -21,6 +77,71 @@ impl Display for LedgerError {
             Self::DuplicateAccount(pubkey) => write!(f, "account {} already exists", pubkey),
             Self::InvalidTransfer(message) => write!(f, "invalid transfer for: {}", message),
             Self::SerializationError(message) => write!(f, "{}", message),
+            Self::MissingSignature(pubkey) => write!(f, "missing signature for {}", pubkey),
+            Self::InvalidSignature(pubkey) => write!(f, "invalid signature for {}", pubkey),
+            Self::InvalidPubkey(pubkey) => write!(f, "{} is not a valid pubkey", pubkey),
+            Self::InvalidAccountKind(kind) => write!(f, "{} is not an account kind", kind),
+            Self::InvalidAuthority(message) => write!(f, "invalid authority: {}", message),
+            Self::StakeNotDelegated(pubkey) => write!(f, "stake {} is not delegated", pubkey),
+            Self::StakeAlreadyDeactivated(pubkey) => {
+                write!(f, "stake {} is already deactivated", pubkey)
+            }
+            Self::StakeStillActive { pubkey, status } => write!(
+                f,
+                "stake {} is {}, it must be deactivated before withdrawing",
+                pubkey, status
+            ),
+            Self::InvalidLedgerFile(message) => write!(f, "invalid ledger file: {}", message),
+            Self::RentNotExempt {
+                pubkey,
+                minimum,
+                lamports,
+            } => write!(
+                f,
+                "account {} holds {} lamports, rent exemption requires {}",
+                pubkey, lamports, minimum
+            ),
+            Self::InvalidBlock { slot, reason } => {
+                write!(f, "invalid block at slot {}: {}", slot, reason)
+            }
+            Self::InvalidRecord { location, reason } => {
+                write!(f, "invalid record at {}: {}", location, reason)
+            }
+            Self::ArithmeticOverflow => write!(f, "arithmetic overflow"),
+            Self::InvariantViolation(message) => {
+                write!(f, "ledger invariant violated: {}", message)
+            }
+            Self::InvalidName(name) => write!(
+                f,
+                "{:?} is not a valid account name, names can not be empty or a pubkey",
+                name
+            ),
+            Self::NameTaken { name, pubkey } => {
+                write!(f, "name {} is already taken by {}", name, pubkey)
+            }
+            Self::RateLimitExceeded {
+                pubkey,
+                remaining,
+                requested,
+            } => write!(
+                f,
+                "faucet rate limit of {}: {} lamports requested, {} left this epoch",
+                pubkey, requested, remaining
+            ),
+            Self::AccountNotEmpty { pubkey, reason } => {
+                write!(f, "account {} can not be closed, {}", pubkey, reason)
+            }
+            Self::InvalidNewAccount { pubkey, reason } => {
+                write!(f, "account {} can not be created, {}", pubkey, reason)
+            }
+            Self::ProgramFailed { program, reason } => {
+                write!(f, "program {} failed: {}", program, reason)
+            }
+            Self::ComputeBudgetExceeded { program, budget } => write!(
+                f,
+                "program {} exceeded its budget of {} compute units",
+                program, budget
+            ),
         }
     }
 }
@@ -0,0 +1,181 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+use crate::pkg::{errors::LedgerError, interchange::option_pubkey_str, processor::checked_add};
+
+/// Who may airdrop lamports out of nothing or withdraw them out of the
+/// ledger, and how many each account may get or lose per epoch. The default
+/// policy has no authority and refuses both.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize, Serialize, Deserialize,
+)]
+pub struct FaucetPolicy {
+    #[serde(default, with = "option_pubkey_str")]
+    pub authority: Option<Pubkey>,
+    /// lamports airdropped to one account per epoch, unlimited when unset
+    pub airdrop_limit: Option<u64>,
+    /// lamports withdrawn from one account per epoch, unlimited when unset
+    pub withdraw_limit: Option<u64>,
+}
+
+impl FaucetPolicy {
+    pub fn new(authority: Pubkey) -> Self {
+        Self {
+            authority: Some(authority),
+            airdrop_limit: None,
+            withdraw_limit: None,
+        }
+    }
+
+    pub fn with_airdrop_limit(mut self, limit: u64) -> Self {
+        self.airdrop_limit = Some(limit);
+        self
+    }
+
+    pub fn with_withdraw_limit(mut self, limit: u64) -> Self {
+        self.withdraw_limit = Some(limit);
+        self
+    }
+}
+
+/// Lamports airdropped to and withdrawn from one account during `epoch`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct FaucetUsage {
+    pub epoch: u64,
+    pub airdropped: u64,
+    pub withdrawn: u64,
+}
+
+/// Faucet policy and the usage it limits, saved with the ledger.
+#[derive(Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Faucet {
+    pub policy: FaucetPolicy,
+    usage: BTreeMap<Pubkey, FaucetUsage>,
+}
+
+impl Faucet {
+    /// Usage of `pubkey` in `epoch`, the usage of previous epochs is
+    /// forgotten.
+    pub fn usage(&self, pubkey: &Pubkey, epoch: u64) -> FaucetUsage {
+        match self.usage.get(pubkey) {
+            Some(usage) if usage.epoch == epoch => *usage,
+            _ => FaucetUsage {
+                epoch,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Usage of `pubkey` once `amount` is airdropped to it, without
+    /// recording it.
+    pub fn check_airdrop(
+        &self,
+        authority: &Pubkey,
+        pubkey: &Pubkey,
+        amount: u64,
+        epoch: u64,
+    ) -> Result<FaucetUsage, LedgerError> {
+        self.check_authority(authority)?;
+        let mut usage = self.usage(pubkey, epoch);
+        usage.airdropped =
+            check_limit(pubkey, usage.airdropped, amount, self.policy.airdrop_limit)?;
+
+        Ok(usage)
+    }
+
+    /// Usage of `pubkey` once `amount` is withdrawn from it, without
+    /// recording it.
+    pub fn check_withdraw(
+        &self,
+        authority: &Pubkey,
+        pubkey: &Pubkey,
+        amount: u64,
+        epoch: u64,
+    ) -> Result<FaucetUsage, LedgerError> {
+        self.check_authority(authority)?;
+        let mut usage = self.usage(pubkey, epoch);
+        usage.withdrawn = check_limit(pubkey, usage.withdrawn, amount, self.policy.withdraw_limit)?;
+
+        Ok(usage)
+    }
+
+    pub fn record(&mut self, pubkey: Pubkey, usage: FaucetUsage) {
+        self.usage.insert(pubkey, usage);
+    }
+
+    pub fn check_authority(&self, authority: &Pubkey) -> Result<(), LedgerError> {
+        match &self.policy.authority {
+            Some(expected) if expected == authority => Ok(()),
+            Some(expected) => Err(LedgerError::InvalidAuthority(format!(
+                "{} is not the faucet authority {}",
+                authority, expected
+            ))),
+            None => Err(LedgerError::InvalidAuthority(String::from(
+                "the ledger has no faucet authority",
+            ))),
+        }
+    }
+}
+
+/// `used` plus `amount` when it stays within `limit`.
+fn check_limit(
+    pubkey: &Pubkey,
+    used: u64,
+    amount: u64,
+    limit: Option<u64>,
+) -> Result<u64, LedgerError> {
+    let total = checked_add(used, amount)?;
+    match limit {
+        Some(limit) if total > limit => Err(LedgerError::RateLimitExceeded {
+            pubkey: pubkey.to_string(),
+            remaining: limit.saturating_sub(used),
+            requested: amount,
+        }),
+        _ => Ok(total),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faucet_limits() {
+        let authority = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let mut faucet = Faucet::default();
+        assert!(matches!(
+            faucet.check_airdrop(&authority, &wallet, 1, 0),
+            Err(LedgerError::InvalidAuthority(_))
+        ));
+
+        faucet.policy = FaucetPolicy::new(authority)
+            .with_airdrop_limit(100)
+            .with_withdraw_limit(10);
+        assert!(matches!(
+            faucet.check_airdrop(&wallet, &wallet, 1, 0),
+            Err(LedgerError::InvalidAuthority(_))
+        ));
+        let usage = faucet.check_airdrop(&authority, &wallet, 60, 0).unwrap();
+        faucet.record(wallet, usage);
+        assert!(matches!(
+            faucet.check_airdrop(&authority, &wallet, 50, 0),
+            Err(LedgerError::RateLimitExceeded { remaining: 40, .. })
+        ));
+        let usage = faucet.check_withdraw(&authority, &wallet, 10, 0).unwrap();
+        assert_eq!(usage.airdropped, 60);
+        assert_eq!(usage.withdrawn, 10);
+
+        // the limits start over every epoch
+        assert_eq!(
+            faucet.check_airdrop(&authority, &wallet, 100, 1).unwrap(),
+            FaucetUsage {
+                epoch: 1,
+                airdropped: 100,
+                withdrawn: 0,
+            }
+        );
+    }
+}
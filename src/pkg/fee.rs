@@ -0,0 +1,81 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::pkg::{errors::LedgerError, interchange::option_pubkey_str, transaction::Transaction};
+
+/// Fees charged to the fee payer of every transaction. The default schedule
+/// is free.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize, Serialize, Deserialize,
+)]
+pub struct FeeSchedule {
+    pub lamports_per_signature: u64,
+    pub lamports_per_instruction: u64,
+    /// wallet receiving the fees, they are burned when unset
+    #[serde(default, with = "option_pubkey_str")]
+    pub collector: Option<Pubkey>,
+}
+
+impl FeeSchedule {
+    pub fn new(lamports_per_signature: u64, lamports_per_instruction: u64) -> Self {
+        Self {
+            lamports_per_signature,
+            lamports_per_instruction,
+            collector: None,
+        }
+    }
+
+    pub fn with_collector(mut self, collector: Pubkey) -> Self {
+        self.collector = Some(collector);
+        self
+    }
+
+    /// Signature and instruction fees of `tx` plus its priority fee.
+    pub fn fee(&self, tx: &Transaction) -> Result<u64, LedgerError> {
+        let signatures = tx.signers().len() as u64;
+        let instructions = tx.instructions.len() as u64;
+
+        self.lamports_per_signature
+            .checked_mul(signatures)
+            .and_then(|fee| {
+                fee.checked_add(self.lamports_per_instruction.checked_mul(instructions)?)
+            })
+            .and_then(|fee| fee.checked_add(tx.priority_fee))
+            .ok_or(LedgerError::ArithmeticOverflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::transaction::Instruction;
+
+    #[test]
+    fn test_fee_schedule() {
+        let from = Pubkey::new_unique();
+        let stake = Pubkey::new_unique();
+        let tx = Transaction::new(vec![
+            Instruction::Transfer {
+                from,
+                to: Pubkey::new_unique(),
+                amount: 1,
+            },
+            Instruction::DelegateStake {
+                from,
+                stake,
+                validator: Pubkey::new_unique(),
+                amount: 1,
+            },
+        ])
+        .with_priority_fee(7);
+
+        assert_eq!(FeeSchedule::default().fee(&tx).unwrap(), 7);
+        // two signers, from and stake, and two instructions
+        assert_eq!(FeeSchedule::new(100, 10).fee(&tx).unwrap(), 227);
+        assert!(matches!(
+            FeeSchedule::new(u64::MAX, 0).fee(&tx),
+            Err(LedgerError::ArithmeticOverflow)
+        ));
+    }
+}
@@ -0,0 +1,781 @@
+use borsh::{BorshDeserialize, BorshSerialize, to_vec};
+use solana_sdk::hash::hash;
+
+use crate::pkg::{
+    account::Account, errors::LedgerError, faucet::Faucet, fee::FeeSchedule, names::NameRegistry,
+    processor::AccountClosure, rent::Rent,
+};
+
+pub const MAGIC: [u8; 4] = *b"LDGR";
+pub const CURRENT_VERSION: u16 = 7;
+
+/// Fixed size header written in front of the Borsh encoded `LedgerFile`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+struct FileHeader {
+    magic: [u8; 4],
+    version: u16,
+    account_count: u64,
+    /// SHA-256 of the payload following the header
+    checksum: [u8; 32],
+}
+
+const HEADER_LEN: usize = 4 + 2 + 8 + 32;
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct LedgerFile {
+    pub epoch: u64,
+    pub rent: Rent,
+    pub accounts: Vec<Account>,
+    pub names: NameRegistry,
+    pub faucet: Faucet,
+    pub closures: Vec<AccountClosure>,
+    pub fees: FeeSchedule,
+    /// what migrating an older layout dropped, one message each
+    #[borsh(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// Older files hold no rent schedule, those ledgers charged no rent.
+impl Default for LedgerFile {
+    fn default() -> Self {
+        Self {
+            epoch: 0,
+            rent: Rent::free(),
+            accounts: Vec::new(),
+            names: NameRegistry::default(),
+            faucet: Faucet::default(),
+            closures: Vec::new(),
+            fees: FeeSchedule::default(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Encodes a `LedgerFile` in the current version without cloning accounts.
+pub fn encode(
+    epoch: u64,
+    rent: Rent,
+    accounts: &[&Account],
+    names: &NameRegistry,
+    faucet: &Faucet,
+    closures: &[AccountClosure],
+    fees: &FeeSchedule,
+) -> Result<Vec<u8>, LedgerError> {
+    let payload = to_vec(&(epoch, rent, accounts, names, faucet, closures, fees))
+        .map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    let header = FileHeader {
+        magic: MAGIC,
+        version: CURRENT_VERSION,
+        account_count: accounts.len() as u64,
+        checksum: hash(&payload).to_bytes(),
+    };
+
+    let mut buff =
+        to_vec(&header).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    buff.extend(payload);
+    Ok(buff)
+}
+
+/// Decodes a ledger file of any known version. Files written before the
+/// header existed are read as version 0, or as the name keyed layout.
+pub fn decode(buff: &[u8]) -> Result<LedgerFile, LedgerError> {
+    if !buff.starts_with(&MAGIC) {
+        if let Ok(accounts) = v0::decode(buff) {
+            return v1::migrate(0, accounts);
+        }
+        return named::decode(buff).map_err(|_| {
+            LedgerError::InvalidLedgerFile(String::from("missing magic bytes, not a ledger file"))
+        });
+    }
+    if buff.len() < HEADER_LEN {
+        return Err(LedgerError::InvalidLedgerFile(String::from(
+            "file is truncated, header is incomplete",
+        )));
+    }
+
+    let header = FileHeader::try_from_slice(&buff[..HEADER_LEN])
+        .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+    let payload = &buff[HEADER_LEN..];
+    if hash(payload).to_bytes() != header.checksum {
+        return Err(LedgerError::InvalidLedgerFile(String::from(
+            "checksum mismatch, file is truncated or corrupt",
+        )));
+    }
+
+    let file = match header.version {
+        1 => {
+            let file = v1::LedgerFile1::try_from_slice(payload)
+                .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+            v1::migrate(file.epoch, file.accounts)?
+        }
+        2 => {
+            let (epoch, accounts) = <(u64, Vec<Account>)>::try_from_slice(payload)
+                .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+            LedgerFile {
+                epoch,
+                accounts,
+                ..Default::default()
+            }
+        }
+        3 => {
+            let (epoch, accounts, names) =
+                <(u64, Vec<Account>, NameRegistry)>::try_from_slice(payload)
+                    .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+            LedgerFile {
+                epoch,
+                accounts,
+                names,
+                ..Default::default()
+            }
+        }
+        4 => {
+            let (epoch, accounts, names, faucet) =
+                <(u64, Vec<Account>, NameRegistry, Faucet)>::try_from_slice(payload)
+                    .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+            LedgerFile {
+                epoch,
+                accounts,
+                names,
+                faucet,
+                ..Default::default()
+            }
+        }
+        5 => {
+            let (epoch, accounts, names, faucet, closures) =
+                <(u64, Vec<Account>, NameRegistry, Faucet, Vec<AccountClosure>)>::try_from_slice(
+                    payload,
+                )
+                .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+            LedgerFile {
+                epoch,
+                accounts,
+                names,
+                faucet,
+                closures,
+                ..Default::default()
+            }
+        }
+        6 => {
+            let (epoch, accounts, names, faucet, closures, fees) =
+                <(
+                    u64,
+                    Vec<Account>,
+                    NameRegistry,
+                    Faucet,
+                    Vec<AccountClosure>,
+                    FeeSchedule,
+                )>::try_from_slice(payload)
+                .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?;
+            LedgerFile {
+                epoch,
+                accounts,
+                names,
+                faucet,
+                closures,
+                fees,
+                ..Default::default()
+            }
+        }
+        7 => LedgerFile::try_from_slice(payload)
+            .map_err(|err| LedgerError::InvalidLedgerFile(err.to_string()))?,
+        version => {
+            return Err(LedgerError::InvalidLedgerFile(format!(
+                "unsupported format version {}, latest known is {}",
+                version, CURRENT_VERSION
+            )));
+        }
+    };
+    if file.accounts.len() as u64 != header.account_count {
+        return Err(LedgerError::InvalidLedgerFile(format!(
+            "header announces {} accounts, file holds {}",
+            header.account_count,
+            file.accounts.len()
+        )));
+    }
+
+    Ok(file)
+}
+
+/// Layout of the headerless files, a bare `Vec<Account>` where stake
+/// accounts had no activation epochs yet.
+mod v0 {
+    use borsh::BorshDeserialize;
+
+    use super::v1::{Account1, AccountType1};
+
+    #[derive(BorshDeserialize)]
+    pub enum AccountType0 {
+        Wallet {
+            balance: u64,
+        },
+        Program {
+            executable: bool,
+            program_data: Vec<u8>,
+        },
+        TokenAccount {
+            mint: String,
+            token_balance: u64,
+            delegate: Option<String>,
+        },
+        Stake {
+            validator: String,
+            staked_amount: u64,
+        },
+    }
+
+    #[derive(BorshDeserialize)]
+    pub struct Account0 {
+        pubkey: String,
+        owner: String,
+        lamports: u64,
+        account_type: AccountType0,
+        created_at: u64,
+    }
+
+    impl From<Account0> for Account1 {
+        fn from(account: Account0) -> Self {
+            let account_type = match account.account_type {
+                AccountType0::Wallet { balance } => AccountType1::Wallet { balance },
+                AccountType0::Program {
+                    executable,
+                    program_data,
+                } => AccountType1::Program {
+                    executable,
+                    program_data,
+                },
+                AccountType0::TokenAccount {
+                    mint,
+                    token_balance,
+                    delegate,
+                } => AccountType1::TokenAccount {
+                    mint,
+                    token_balance,
+                    delegate,
+                },
+                AccountType0::Stake {
+                    validator,
+                    staked_amount,
+                } => AccountType1::Stake {
+                    validator,
+                    staked_amount,
+                    activation_epoch: None,
+                    deactivation_epoch: None,
+                },
+            };
+
+            Account1 {
+                pubkey: account.pubkey,
+                owner: account.owner,
+                lamports: account.lamports,
+                account_type,
+                created_at: account.created_at,
+            }
+        }
+    }
+
+    pub fn decode(buff: &[u8]) -> Result<Vec<Account1>, std::io::Error> {
+        let accounts = Vec::<Account0>::try_from_slice(buff)?;
+        Ok(accounts.into_iter().map(Account1::from).collect())
+    }
+}
+
+/// Layout where keys were stored as strings and owners as program names
+/// like "system" or "Token".
+mod v1 {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::LedgerFile;
+    use crate::pkg::{
+        account::{Account, AccountType, parse_pubkey},
+        errors::LedgerError,
+    };
+
+    #[derive(BorshSerialize, BorshDeserialize)]
+    pub enum AccountType1 {
+        Wallet {
+            balance: u64,
+        },
+        Program {
+            executable: bool,
+            program_data: Vec<u8>,
+        },
+        TokenAccount {
+            mint: String,
+            token_balance: u64,
+            delegate: Option<String>,
+        },
+        Stake {
+            validator: String,
+            staked_amount: u64,
+            activation_epoch: Option<u64>,
+            deactivation_epoch: Option<u64>,
+        },
+        Mint {
+            decimals: u8,
+            supply: u64,
+            mint_authority: Option<String>,
+            freeze_authority: Option<String>,
+        },
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize)]
+    pub struct Account1 {
+        pub pubkey: String,
+        pub owner: String,
+        pub lamports: u64,
+        pub account_type: AccountType1,
+        pub created_at: u64,
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize)]
+    pub struct LedgerFile1 {
+        pub epoch: u64,
+        pub accounts: Vec<Account1>,
+    }
+
+    /// Converts the accounts to typed keys. Owners are derived from the
+    /// account type. An account whose own key, mint or validator is not a
+    /// valid pubkey can not be migrated, the error names it and the field.
+    /// Invalid delegates and authorities are cleared with a warning.
+    pub fn migrate(epoch: u64, accounts: Vec<Account1>) -> Result<LedgerFile, LedgerError> {
+        let mut warnings = Vec::new();
+        let accounts = accounts
+            .into_iter()
+            .map(|account| migrate_account(account, &mut warnings))
+            .collect::<Result<_, _>>()?;
+
+        Ok(LedgerFile {
+            epoch,
+            accounts,
+            warnings,
+            ..Default::default()
+        })
+    }
+
+    /// `value` of the `field` of the account `pubkey` as a key.
+    fn key(pubkey: &Pubkey, field: &str, value: &str) -> Result<Pubkey, LedgerError> {
+        parse_pubkey(value).map_err(|_| {
+            LedgerError::InvalidLedgerFile(format!(
+                "{} {} of account {} is not a valid pubkey",
+                field, value, pubkey
+            ))
+        })
+    }
+
+    /// `value` of the optional `field` of the account `pubkey` as a key,
+    /// `None` with a warning when it is not one.
+    fn optional_key(
+        pubkey: &Pubkey,
+        field: &str,
+        value: Option<String>,
+        warnings: &mut Vec<String>,
+    ) -> Option<Pubkey> {
+        let value = value?;
+        match key(pubkey, field, &value) {
+            Ok(key) => Some(key),
+            Err(_) => {
+                warnings.push(format!(
+                    "{} {} of account {} is not a valid pubkey, it was cleared",
+                    field, value, pubkey
+                ));
+                None
+            }
+        }
+    }
+
+    fn migrate_account(
+        account: Account1,
+        warnings: &mut Vec<String>,
+    ) -> Result<Account, LedgerError> {
+        let pubkey = parse_pubkey(&account.pubkey).map_err(|_| {
+            LedgerError::InvalidLedgerFile(format!(
+                "account key {} is not a valid pubkey",
+                account.pubkey
+            ))
+        })?;
+        let account_type = match account.account_type {
+            AccountType1::Wallet { balance } => AccountType::Wallet { balance },
+            AccountType1::Program {
+                executable,
+                program_data,
+            } => AccountType::Program {
+                executable,
+                program_data,
+            },
+            AccountType1::TokenAccount {
+                mint,
+                token_balance,
+                delegate,
+            } => AccountType::TokenAccount {
+                mint: key(&pubkey, "mint", &mint)?,
+                token_balance,
+                delegate: optional_key(&pubkey, "delegate", delegate, warnings),
+            },
+            AccountType1::Stake {
+                validator,
+                staked_amount,
+                activation_epoch,
+                deactivation_epoch,
+            } => AccountType::Stake {
+                validator: key(&pubkey, "validator", &validator)?,
+                staked_amount,
+                activation_epoch,
+                deactivation_epoch,
+            },
+            AccountType1::Mint {
+                decimals,
+                supply,
+                mint_authority,
+                freeze_authority,
+            } => AccountType::Mint {
+                decimals,
+                supply,
+                mint_authority: optional_key(&pubkey, "mint authority", mint_authority, warnings),
+                freeze_authority: optional_key(
+                    &pubkey,
+                    "freeze authority",
+                    freeze_authority,
+                    warnings,
+                ),
+            },
+        };
+
+        Ok(Account::from_parts(
+            pubkey,
+            account_type.owner(),
+            account.lamports,
+            account_type,
+            account.created_at,
+        ))
+    }
+}
+
+/// Layout of the name keyed ledger that lived next to this one, a bare
+/// `HashMap<String, Account>` of `u32` wallet balances.
+mod named {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use solana_sdk::{hash::hash, pubkey::Pubkey};
+    use std::collections::HashMap;
+
+    use super::LedgerFile;
+    use crate::pkg::account::{Account, AccountType};
+
+    #[derive(BorshSerialize, BorshDeserialize)]
+    pub struct NamedAccount {
+        pub balance: u32,
+        pub owner: String,
+    }
+
+    /// Turns every named account into a wallet. Their key is the hash of
+    /// their name, the name is kept to look them up.
+    pub fn decode(buff: &[u8]) -> Result<LedgerFile, std::io::Error> {
+        let named = HashMap::<String, NamedAccount>::try_from_slice(buff)?;
+
+        let mut file = LedgerFile::default();
+        for (name, account) in named {
+            let pubkey = key(&name);
+            let account_type = AccountType::Wallet {
+                balance: account.balance as u64,
+            };
+            file.accounts.push(Account::from_parts(
+                pubkey,
+                account_type.owner(),
+                account.balance as u64,
+                account_type,
+                0,
+            ));
+            // the account stays reachable by key when its name is not valid
+            let _ = file.names.register(&name, pubkey);
+        }
+
+        Ok(file)
+    }
+
+    pub fn key(name: &str) -> Pubkey {
+        Pubkey::new_from_array(hash(name.as_bytes()).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::account::{AccountType, STAKE_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID};
+    use solana_sdk::pubkey::Pubkey;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_format_round_trip() {
+        let file = LedgerFile {
+            epoch: 7,
+            accounts: vec![
+                Account::new(AccountType::Wallet { balance: 10 }),
+                Account::new(AccountType::Stake {
+                    validator: Pubkey::new_unique(),
+                    staked_amount: 5,
+                    activation_epoch: Some(3),
+                    deactivation_epoch: None,
+                }),
+            ],
+            ..Default::default()
+        };
+        let mut names = NameRegistry::new();
+        names.register("Savings", file.accounts[0].pubkey).unwrap();
+
+        let buff = encode(
+            file.epoch,
+            Rent::default(),
+            &file.accounts.iter().collect::<Vec<_>>(),
+            &names,
+            &Faucet::default(),
+            &[],
+            &FeeSchedule::new(5, 1),
+        )
+        .unwrap();
+        assert!(buff.starts_with(&MAGIC));
+        let decoded = decode(&buff).unwrap();
+        assert_eq!(decoded.epoch, 7);
+        assert_eq!(decoded.fees, FeeSchedule::new(5, 1));
+        assert_eq!(decoded.rent, Rent::default());
+        assert_eq!(decoded.accounts.len(), 2);
+        assert_eq!(decoded.accounts[0].pubkey, file.accounts[0].pubkey);
+        assert_eq!(decoded.names, names);
+    }
+
+    #[test]
+    fn test_format_rejects_corrupt_files() {
+        let account = Account::new(AccountType::Wallet { balance: 10 });
+        let buff = encode(
+            0,
+            Rent::free(),
+            &[&account],
+            &NameRegistry::new(),
+            &Faucet::default(),
+            &[],
+            &FeeSchedule::default(),
+        )
+        .unwrap();
+
+        let mut corrupt = buff.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        let truncated = &buff[..buff.len() - 3];
+        let mut future_version = buff.clone();
+        future_version[4] = 9;
+        for bad in [
+            &corrupt[..],
+            truncated,
+            &future_version[..],
+            &buff[..10],
+            b"hello random set of bytes passing by",
+        ] {
+            let err = decode(bad).unwrap_err();
+            assert!(matches!(err, LedgerError::InvalidLedgerFile(_)));
+        }
+    }
+
+    #[test]
+    fn test_format_migrates_headerless_files() {
+        // bare Vec<Account> with one stake account, as written before the header
+        let legacy = |validator: &str| {
+            let mut legacy = to_vec(&1u32).unwrap();
+            legacy.extend(to_vec(&String::from("11111111111111111111111111111111")).unwrap());
+            legacy.extend(to_vec(&String::from("system")).unwrap());
+            legacy.extend(to_vec(&40u64).unwrap());
+            legacy.push(3);
+            legacy.extend(to_vec(&validator.to_string()).unwrap());
+            legacy.extend(to_vec(&40u64).unwrap());
+            legacy.extend(to_vec(&1_700_000_000u64).unwrap());
+            legacy
+        };
+
+        let validator_key = Pubkey::new_unique();
+        let decoded = decode(&legacy(&validator_key.to_string())).unwrap();
+        assert_eq!(decoded.accounts.len(), 1);
+        let account = &decoded.accounts[0];
+        assert_eq!(account.lamports, 40);
+        assert_eq!(account.created_at(), 1_700_000_000);
+        assert_eq!(*account.owner(), STAKE_PROGRAM_ID);
+        if let AccountType::Stake {
+            validator,
+            staked_amount,
+            activation_epoch,
+            ..
+        } = account.account_type
+        {
+            assert_eq!(validator, validator_key);
+            assert_eq!(staked_amount, 40);
+            assert_eq!(activation_epoch, None);
+        } else {
+            panic!("account is not a stake account");
+        }
+
+        // "validator" never was a valid key
+        let err = decode(&legacy("validator")).unwrap_err();
+        assert!(
+            matches!(&err, LedgerError::InvalidLedgerFile(message) if message.contains("validator validator of account 11111111111111111111111111111111"))
+        );
+    }
+
+    #[test]
+    fn test_format_migrates_string_keys() {
+        let pubkey = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let delegate_key = Pubkey::new_unique();
+        let encode = |delegate: String| {
+            let file = v1::LedgerFile1 {
+                epoch: 4,
+                accounts: vec![v1::Account1 {
+                    pubkey: pubkey.to_string(),
+                    owner: String::from("Token"),
+                    lamports: 9,
+                    account_type: v1::AccountType1::TokenAccount {
+                        mint: mint.to_string(),
+                        token_balance: 9,
+                        delegate: Some(delegate),
+                    },
+                    created_at: 1_700_000_000,
+                }],
+            };
+            let payload = to_vec(&file).unwrap();
+            let mut buff = to_vec(&FileHeader {
+                magic: MAGIC,
+                version: 1,
+                account_count: 1,
+                checksum: hash(&payload).to_bytes(),
+            })
+            .unwrap();
+            buff.extend(payload);
+            buff
+        };
+
+        let decoded = decode(&encode(delegate_key.to_string())).unwrap();
+        assert_eq!(decoded.epoch, 4);
+        let account = &decoded.accounts[0];
+        assert_eq!(account.pubkey, pubkey);
+        assert_eq!(*account.owner(), TOKEN_PROGRAM_ID);
+        if let AccountType::TokenAccount {
+            mint: token_mint,
+            delegate,
+            ..
+        } = account.account_type
+        {
+            assert_eq!(token_mint, mint);
+            assert_eq!(delegate, Some(delegate_key));
+        } else {
+            panic!("account is not a token account");
+        }
+
+        // an invalid delegate is dropped, the account is kept
+        let decoded = decode(&encode(String::from("a wild string"))).unwrap();
+        assert!(matches!(
+            decoded.accounts[0].account_type,
+            AccountType::TokenAccount { delegate: None, .. }
+        ));
+        assert_eq!(
+            decoded.warnings,
+            vec![format!(
+                "delegate a wild string of account {} is not a valid pubkey, it was cleared",
+                pubkey
+            )]
+        );
+    }
+
+    #[test]
+    fn test_format_migrates_baseline_demo_ledger() {
+        // the headerless file the baseline demo saved, its token account
+        // delegates to "a wild string"
+        let account = |owner: &str, lamports: u64, account_type: Vec<u8>| {
+            let mut account = to_vec(&Pubkey::new_unique().to_string()).unwrap();
+            account.extend(to_vec(&owner.to_string()).unwrap());
+            account.extend(to_vec(&lamports).unwrap());
+            account.extend(account_type);
+            account.extend(to_vec(&1_700_000_000u64).unwrap());
+            account
+        };
+        let wallet = |balance: u64| [vec![0], to_vec(&balance).unwrap()].concat();
+        let mut demo = to_vec(&5u32).unwrap();
+        demo.extend(account("system", 9_900, wallet(9_900)));
+        demo.extend(account(
+            "BPFLoader",
+            1,
+            [vec![1, 1], to_vec(&b"program data".to_vec()).unwrap()].concat(),
+        ));
+        demo.extend(account(
+            "Token",
+            200_000_000_000,
+            [
+                vec![2],
+                to_vec(&Pubkey::new_unique().to_string()).unwrap(),
+                to_vec(&200_000_000_000u64).unwrap(),
+                to_vec(&Some(String::from("a wild string"))).unwrap(),
+            ]
+            .concat(),
+        ));
+        demo.extend(account(
+            "system",
+            40_000_000_000,
+            [
+                vec![3],
+                to_vec(&Pubkey::new_unique().to_string()).unwrap(),
+                to_vec(&40_000_000_000u64).unwrap(),
+            ]
+            .concat(),
+        ));
+        demo.extend(account("system", 50_000_100, wallet(50_000_100)));
+
+        let decoded = decode(&demo).unwrap();
+        assert_eq!(decoded.accounts.len(), 5);
+        assert_eq!(decoded.warnings.len(), 1);
+        assert!(decoded.warnings[0].starts_with("delegate a wild string of account"));
+        let token_account = decoded
+            .accounts
+            .iter()
+            .find(|account| *account.owner() == TOKEN_PROGRAM_ID)
+            .unwrap();
+        assert!(matches!(
+            token_account.account_type,
+            AccountType::TokenAccount {
+                token_balance: 200_000_000_000,
+                delegate: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_format_migrates_named_ledgers() {
+        // HashMap<String, Account> written by the name keyed ledger
+        let mut legacy = HashMap::new();
+        legacy.insert(
+            String::from("alice"),
+            named::NamedAccount {
+                balance: 30,
+                owner: String::from("system"),
+            },
+        );
+        legacy.insert(
+            String::from("bob"),
+            named::NamedAccount {
+                balance: 12,
+                owner: String::from("system"),
+            },
+        );
+
+        let decoded = decode(&to_vec(&legacy).unwrap()).unwrap();
+        assert_eq!(decoded.epoch, 0);
+        assert_eq!(decoded.accounts.len(), 2);
+        let alice = decoded.names.resolve("Alice").unwrap();
+        assert_eq!(alice, named::key("alice"));
+        let account = decoded
+            .accounts
+            .iter()
+            .find(|account| account.pubkey == alice)
+            .unwrap();
+        assert_eq!(account.lamports, 30);
+        assert_eq!(*account.owner(), SYSTEM_PROGRAM_ID);
+        assert!(matches!(
+            account.account_type,
+            AccountType::Wallet { balance: 30 }
+        ));
+    }
+}
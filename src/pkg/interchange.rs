@@ -0,0 +1,571 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+
+use crate::pkg::{
+    account::{Account, AccountKind, AccountType, parse_pubkey},
+    errors::LedgerError,
+    faucet::FaucetPolicy,
+    fee::FeeSchedule,
+    ledger::Ledger,
+    rent::Rent,
+};
+
+/// JSON document of a whole ledger. Documents holding only the epoch and
+/// the accounts import with the defaults of a new ledger.
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerDocument<T> {
+    epoch: u64,
+    #[serde(default = "Rent::free")]
+    rent: Rent,
+    #[serde(default)]
+    fees: FeeSchedule,
+    #[serde(default)]
+    faucet: FaucetPolicy,
+    #[serde(default)]
+    names: Vec<AccountName>,
+    accounts: Vec<T>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AccountName {
+    name: String,
+    #[serde(with = "pubkey_str")]
+    pubkey: Pubkey,
+}
+
+pub fn account_to_json(account: &Account) -> Result<String, LedgerError> {
+    serde_json::to_string_pretty(account)
+        .map_err(|err| LedgerError::SerializationError(err.to_string()))
+}
+
+pub fn account_from_json(json: &str) -> Result<Account, LedgerError> {
+    let account: Account = serde_json::from_str(json).map_err(|err| invalid("account", err))?;
+    validate(&account).map_err(|reason| invalid("account", reason))?;
+
+    Ok(account)
+}
+
+/// The accounts of `ledger` along with its names, faucet policy and fee and
+/// rent schedules. The faucet usage of the current epoch is not exported.
+pub fn ledger_to_json(ledger: &Ledger) -> Result<String, LedgerError> {
+    serde_json::to_string_pretty(&LedgerDocument {
+        epoch: ledger.epoch(),
+        rent: ledger.rent(),
+        fees: ledger.fees().clone(),
+        faucet: ledger.faucet().policy.clone(),
+        names: ledger
+            .names()
+            .iter()
+            .map(|(name, pubkey)| AccountName {
+                name: name.to_string(),
+                pubkey: *pubkey,
+            })
+            .collect(),
+        accounts: ledger.accounts().collect(),
+    })
+    .map_err(|err| LedgerError::SerializationError(err.to_string()))
+}
+
+/// Builds a ledger from a JSON document, an error names the first account
+/// object that is invalid by its index in `accounts`.
+pub fn ledger_from_json(json: &str) -> Result<Ledger, LedgerError> {
+    let document: LedgerDocument<Value> =
+        serde_json::from_str(json).map_err(|err| invalid("document", err))?;
+
+    let mut accounts = Vec::with_capacity(document.accounts.len());
+    for (index, value) in document.accounts.into_iter().enumerate() {
+        let location = format!("account {}", index);
+        let account: Account =
+            serde_json::from_value(value).map_err(|err| invalid(&location, err))?;
+        validate(&account).map_err(|reason| invalid(&location, reason))?;
+        accounts.push((location, account));
+    }
+    check_duplicates(&accounts)?;
+
+    let names: Vec<(String, Pubkey)> = document
+        .names
+        .into_iter()
+        .map(|name| (name.name, name.pubkey))
+        .collect();
+    Ledger::from_export(
+        document.epoch,
+        accounts.into_iter().map(|(_, account)| account).collect(),
+        &names,
+        document.faucet,
+        document.fees,
+        document.rent,
+    )
+}
+
+/// One CSV row per account, the columns of the other account kinds are left
+/// empty and program data is base64 encoded.
+pub fn accounts_to_csv<'a>(
+    accounts: impl Iterator<Item = &'a Account>,
+) -> Result<String, LedgerError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for account in accounts {
+        writer
+            .serialize(CsvRecord::from(account))
+            .map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    }
+
+    let buff = writer
+        .into_inner()
+        .map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    String::from_utf8(buff).map_err(|err| LedgerError::SerializationError(err.to_string()))
+}
+
+/// Parses and validates every row, an error names the first invalid row by
+/// its line number, the header being line 1.
+pub fn accounts_from_csv(csv: &str) -> Result<Vec<Account>, LedgerError> {
+    Ok(parse_csv(csv)?
+        .into_iter()
+        .map(|(_, account)| account)
+        .collect())
+}
+
+pub fn ledger_to_csv(ledger: &Ledger) -> Result<String, LedgerError> {
+    accounts_to_csv(ledger.accounts())
+}
+
+/// Builds a ledger at epoch `0` from CSV rows, see `accounts_from_csv`.
+pub fn ledger_from_csv(csv: &str) -> Result<Ledger, LedgerError> {
+    build_ledger(0, parse_csv(csv)?)
+}
+
+fn parse_csv(csv: &str) -> Result<Vec<(String, Account)>, LedgerError> {
+    let mut reader = csv::Reader::from_reader(csv.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|err| invalid("header", err))?
+        .clone();
+
+    let mut accounts = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let mut location = format!("row {}", index + 2);
+        let record = record.map_err(|err| invalid(&location, err))?;
+        if let Some(position) = record.position() {
+            location = format!("row {}", position.line());
+        }
+
+        let row: CsvRecord = record
+            .deserialize(Some(&headers))
+            .map_err(|err| invalid(&location, err))?;
+        let account = Account::try_from(row)
+            .and_then(|account| validate(&account).map(|_| account))
+            .map_err(|reason| invalid(&location, reason))?;
+        accounts.push((location, account));
+    }
+
+    Ok(accounts)
+}
+
+fn build_ledger(epoch: u64, accounts: Vec<(String, Account)>) -> Result<Ledger, LedgerError> {
+    check_duplicates(&accounts)?;
+
+    Ledger::from_accounts(
+        epoch,
+        accounts.into_iter().map(|(_, account)| account).collect(),
+    )
+}
+
+fn check_duplicates(accounts: &[(String, Account)]) -> Result<(), LedgerError> {
+    let mut seen = HashSet::new();
+    for (location, account) in accounts {
+        if !seen.insert(account.pubkey) {
+            return Err(invalid(
+                location,
+                format!("account {} is duplicated", account.pubkey),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks what deserializing can not: the owner is the one of the account
+/// type and a wallet balance matches its lamports. Program data accounts are
+/// owned by whichever program they hold the state of.
+fn validate(account: &Account) -> Result<(), String> {
+    let program_data = matches!(
+        account.account_type,
+        AccountType::Program {
+            executable: false,
+            ..
+        }
+    );
+    if !program_data && *account.owner() != account.account_type.owner() {
+        return Err(format!(
+            "owner {} does not match a {} account, expected {}",
+            account.owner(),
+            account.kind(),
+            account.account_type.owner()
+        ));
+    }
+
+    match &account.account_type {
+        AccountType::Wallet { balance } if *balance != account.lamports => Err(format!(
+            "wallet balance {} differs from its {} lamports",
+            balance, account.lamports
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn invalid(location: &str, reason: impl ToString) -> LedgerError {
+    LedgerError::InvalidRecord {
+        location: location.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Flat CSV row holding the fields of every account kind.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CsvRecord {
+    pubkey: String,
+    kind: String,
+    owner: String,
+    lamports: u64,
+    created_at: u64,
+    balance: Option<u64>,
+    executable: Option<bool>,
+    program_data: Option<String>,
+    mint: Option<String>,
+    token_balance: Option<u64>,
+    delegate: Option<String>,
+    validator: Option<String>,
+    staked_amount: Option<u64>,
+    activation_epoch: Option<u64>,
+    deactivation_epoch: Option<u64>,
+    decimals: Option<u8>,
+    supply: Option<u64>,
+    mint_authority: Option<String>,
+    freeze_authority: Option<String>,
+}
+
+impl From<&Account> for CsvRecord {
+    fn from(account: &Account) -> Self {
+        let mut record = CsvRecord {
+            pubkey: account.pubkey.to_string(),
+            kind: account.kind().to_string(),
+            owner: account.owner().to_string(),
+            lamports: account.lamports,
+            created_at: account.created_at(),
+            ..Default::default()
+        };
+
+        match &account.account_type {
+            AccountType::Wallet { balance } => record.balance = Some(*balance),
+            AccountType::Program {
+                executable,
+                program_data,
+            } => {
+                record.executable = Some(*executable);
+                record.program_data = Some(base64_bytes::encode(program_data));
+            }
+            AccountType::TokenAccount {
+                mint,
+                token_balance,
+                delegate,
+            } => {
+                record.mint = Some(mint.to_string());
+                record.token_balance = Some(*token_balance);
+                record.delegate = delegate.map(|key| key.to_string());
+            }
+            AccountType::Stake {
+                validator,
+                staked_amount,
+                activation_epoch,
+                deactivation_epoch,
+            } => {
+                record.validator = Some(validator.to_string());
+                record.staked_amount = Some(*staked_amount);
+                record.activation_epoch = *activation_epoch;
+                record.deactivation_epoch = *deactivation_epoch;
+            }
+            AccountType::Mint {
+                decimals,
+                supply,
+                mint_authority,
+                freeze_authority,
+            } => {
+                record.decimals = Some(*decimals);
+                record.supply = Some(*supply);
+                record.mint_authority = mint_authority.map(|key| key.to_string());
+                record.freeze_authority = freeze_authority.map(|key| key.to_string());
+            }
+        }
+
+        record
+    }
+}
+
+impl TryFrom<CsvRecord> for Account {
+    type Error = String;
+
+    fn try_from(record: CsvRecord) -> Result<Self, Self::Error> {
+        fn required<T>(value: Option<T>, field: &str) -> Result<T, String> {
+            value.ok_or_else(|| format!("missing {}", field))
+        }
+        fn key(value: &str, field: &str) -> Result<Pubkey, String> {
+            parse_pubkey(value).map_err(|err| format!("{}: {}", field, err))
+        }
+        fn optional_key(value: Option<String>, field: &str) -> Result<Option<Pubkey>, String> {
+            value.map(|value| key(&value, field)).transpose()
+        }
+
+        let kind = record
+            .kind
+            .parse()
+            .map_err(|err: LedgerError| err.to_string())?;
+        let account_type = match kind {
+            AccountKind::Wallet => AccountType::Wallet {
+                balance: required(record.balance, "balance")?,
+            },
+            AccountKind::Program => AccountType::Program {
+                executable: required(record.executable, "executable")?,
+                program_data: base64_bytes::decode(&record.program_data.unwrap_or_default())?,
+            },
+            AccountKind::TokenAccount => AccountType::TokenAccount {
+                mint: key(&required(record.mint, "mint")?, "mint")?,
+                token_balance: required(record.token_balance, "token_balance")?,
+                delegate: optional_key(record.delegate, "delegate")?,
+            },
+            AccountKind::Stake => AccountType::Stake {
+                validator: key(&required(record.validator, "validator")?, "validator")?,
+                staked_amount: required(record.staked_amount, "staked_amount")?,
+                activation_epoch: record.activation_epoch,
+                deactivation_epoch: record.deactivation_epoch,
+            },
+            AccountKind::Mint => AccountType::Mint {
+                decimals: required(record.decimals, "decimals")?,
+                supply: required(record.supply, "supply")?,
+                mint_authority: optional_key(record.mint_authority, "mint_authority")?,
+                freeze_authority: optional_key(record.freeze_authority, "freeze_authority")?,
+            },
+        };
+
+        Ok(Account::from_parts(
+            key(&record.pubkey, "pubkey")?,
+            key(&record.owner, "owner")?,
+            record.lamports,
+            account_type,
+            record.created_at,
+        ))
+    }
+}
+
+/// Serde helpers writing bytes as a standard base64 string.
+pub mod base64_bytes {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn encode(bytes: &[u8]) -> String {
+        STANDARD.encode(bytes)
+    }
+
+    pub fn decode(value: &str) -> Result<Vec<u8>, String> {
+        STANDARD
+            .decode(value)
+            .map_err(|err| format!("invalid base64: {}", err))
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        decode(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serde helpers writing a pubkey as its base58 string, a string that is not
+/// a valid key fails to deserialize.
+pub mod pubkey_str {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    use crate::pkg::account::parse_pubkey;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse_pubkey(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// `pubkey_str` for an optional key, unset keys are written as `null`.
+pub mod option_pubkey_str {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    use crate::pkg::account::parse_pubkey;
+
+    pub fn serialize<S: Serializer>(
+        pubkey: &Option<Pubkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match pubkey {
+            Some(pubkey) => serializer.collect_str(pubkey),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pubkey>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| parse_pubkey(&value).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_ledger() -> Ledger {
+        let mint = Pubkey::new_unique();
+        Ledger::from_accounts(
+            3,
+            vec![
+                Account::new(AccountType::Wallet { balance: 10 }),
+                Account::new(AccountType::Program {
+                    executable: true,
+                    program_data: b"program, with \"quotes\"".to_vec(),
+                }),
+                Account::new(AccountType::TokenAccount {
+                    mint,
+                    token_balance: 5,
+                    delegate: Some(Pubkey::new_unique()),
+                }),
+                Account::new(AccountType::Stake {
+                    validator: Pubkey::new_unique(),
+                    staked_amount: 7,
+                    activation_epoch: Some(1),
+                    deactivation_epoch: None,
+                }),
+                Account::with_pubkey(
+                    mint,
+                    AccountType::Mint {
+                        decimals: 6,
+                        supply: 5,
+                        mint_authority: Some(Pubkey::new_unique()),
+                        freeze_authority: None,
+                    },
+                ),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn assert_same_accounts(left: &Ledger, right: &Ledger) {
+        assert_eq!(left.accounts().count(), right.accounts().count());
+        for account in left.accounts() {
+            let other = right.get_account(&account.pubkey).unwrap();
+            assert_eq!(
+                account.save_to_bytes().unwrap(),
+                other.save_to_bytes().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_interchange_round_trip() {
+        let ledger = fixture_ledger();
+
+        let from_json = ledger_from_json(&ledger_to_json(&ledger).unwrap()).unwrap();
+        assert_eq!(from_json.epoch(), 3);
+        assert_same_accounts(&ledger, &from_json);
+
+        let from_csv = ledger_from_csv(&ledger_to_csv(&ledger).unwrap()).unwrap();
+        assert_same_accounts(&ledger, &from_csv);
+
+        // the json document keeps the settings of the ledger
+        let mut configured = fixture_ledger();
+        let wallet = configured.accounts_by_type(AccountKind::Wallet)[0].pubkey;
+        let authority = Pubkey::new_unique();
+        configured.register_name("Treasury", &wallet).unwrap();
+        configured
+            .set_fees(FeeSchedule::new(5, 1).with_collector(wallet))
+            .unwrap();
+        configured.set_rent(Rent::default()).unwrap();
+        let from_json = ledger_from_json(&ledger_to_json(&configured).unwrap()).unwrap();
+        assert_eq!(from_json.names(), configured.names());
+        assert_eq!(from_json.fees(), configured.fees());
+        assert_eq!(from_json.rent(), Rent::default());
+        let policy = FaucetPolicy::new(authority).with_airdrop_limit(10);
+        let mut json: Value = serde_json::from_str(&ledger_to_json(&ledger).unwrap()).unwrap();
+        json["faucet"] = serde_json::to_value(&policy).unwrap();
+        let from_json = ledger_from_json(&json.to_string()).unwrap();
+        assert_eq!(from_json.faucet().policy, policy);
+        // documents of accounts only import with the defaults
+        json.as_object_mut()
+            .unwrap()
+            .retain(|key, _| key == "epoch" || key == "accounts");
+        let from_json = ledger_from_json(&json.to_string()).unwrap();
+        assert_eq!(from_json.rent(), Rent::free());
+        assert_eq!(from_json.faucet().policy, FaucetPolicy::default());
+        assert!(from_json.names().is_empty());
+
+        for account in ledger.accounts() {
+            let json = account_to_json(account).unwrap();
+            assert!(json.contains(&format!("\"kind\": \"{}\"", account.kind())));
+            let parsed = account_from_json(&json).unwrap();
+            assert_eq!(parsed.pubkey, account.pubkey);
+        }
+    }
+
+    #[test]
+    fn test_interchange_reports_invalid_records() {
+        let ledger = fixture_ledger();
+
+        let csv = ledger_to_csv(&ledger).unwrap();
+        let mut lines: Vec<String> = csv.lines().map(String::from).collect();
+        lines[3] = lines[3].replacen(',', ",not_a_kind,", 1);
+        let err = accounts_from_csv(&lines.join("\n")).unwrap_err();
+        assert!(
+            matches!(err, LedgerError::InvalidRecord { ref location, .. } if location == "row 4")
+        );
+
+        let wallet = Account::new(AccountType::Wallet { balance: 1 });
+        let mut json: Value = serde_json::from_str(&ledger_to_json(&ledger).unwrap()).unwrap();
+        let accounts = json["accounts"].as_array_mut().unwrap();
+        let mut bad_wallet = serde_json::to_value(&wallet).unwrap();
+        bad_wallet["lamports"] = Value::from(2);
+        accounts.push(bad_wallet);
+        let err = ledger_from_json(&json.to_string()).unwrap_err();
+        let expected_err = LedgerError::InvalidRecord {
+            location: String::from("account 5"),
+            reason: String::from("wallet balance 1 differs from its 2 lamports"),
+        };
+        assert_eq!(err.to_string(), expected_err.to_string());
+
+        let mut duplicated: Value =
+            serde_json::from_str(&ledger_to_json(&ledger).unwrap()).unwrap();
+        let first = duplicated["accounts"][0].clone();
+        duplicated["accounts"].as_array_mut().unwrap().push(first);
+        let err = ledger_from_json(&duplicated.to_string()).unwrap_err();
+        assert!(
+            matches!(err, LedgerError::InvalidRecord { ref location, .. } if location == "account 5")
+        );
+
+        let err = account_from_json(r#"{"pubkey": "not a key"}"#).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidRecord { .. }));
+
+        let token = ledger
+            .accounts()
+            .find(|account| account.kind() == AccountKind::TokenAccount)
+            .unwrap();
+        let mut bad_delegate = serde_json::to_value(token).unwrap();
+        bad_delegate["account_type"]["delegate"] = Value::from("a wild string");
+        let err = account_from_json(&bad_delegate.to_string()).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidRecord { .. }));
+    }
+}
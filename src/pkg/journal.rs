@@ -0,0 +1,183 @@
+use borsh::{BorshDeserialize, BorshSerialize, to_vec};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    fs::{File, OpenOptions, create_dir_all},
+    io::{ErrorKind, Read, Seek, Write},
+    path::Path,
+};
+
+use crate::pkg::{
+    account::Account, errors::LedgerError, faucet::FaucetPolicy, fee::FeeSchedule, rent::Rent,
+    transaction::Transaction,
+};
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub enum JournalEntry {
+    AddAccount(Account),
+    Transaction(Transaction),
+    /// the ledger was saved, every entry before this one is in the snapshot
+    Snapshot,
+    AdvanceEpoch,
+    /// a rent collection pass charged with this schedule
+    CollectRent(Rent),
+    /// fee schedule of the transactions that follow
+    SetFees(FeeSchedule),
+    RegisterName {
+        name: String,
+        pubkey: Pubkey,
+    },
+    RenameAccount {
+        from: String,
+        to: String,
+    },
+    /// faucet policy of the airdrops and withdrawals that follow
+    SetFaucetPolicy(FaucetPolicy),
+    Airdrop {
+        authority: Pubkey,
+        to: Pubkey,
+        amount: u64,
+    },
+    Withdraw {
+        authority: Pubkey,
+        from: Pubkey,
+        amount: u64,
+    },
+    /// rent schedule of the accounts created and collected from then on
+    SetRent(Rent),
+}
+
+/// Largest record `read_records` accepts, a longer length prefix can only
+/// come from a corrupt file.
+pub const MAX_RECORD_LEN: u64 = 64 * 1024 * 1024;
+
+/// Append-only log of every ledger mutation, each entry is written as a
+/// length prefixed Borsh buffer and synced before the ledger applies it.
+#[derive(Debug)]
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    pub fn open(path: &str) -> Result<Self, LedgerError> {
+        Ok(Self {
+            file: open_append(path)?,
+        })
+    }
+
+    pub fn append(&self, entry: &JournalEntry) -> Result<(), LedgerError> {
+        append_record(&self.file, entry)
+    }
+
+    /// Reads every complete entry. A torn entry at the end of the file comes
+    /// from a crash mid-append, it was never applied so it is skipped.
+    pub fn read_entries(path: &str) -> Result<Vec<JournalEntry>, LedgerError> {
+        read_records(path)
+    }
+}
+
+/// Opens `path` for appending, creating it and its directory when missing.
+pub fn open_append(path: &str) -> Result<File, LedgerError> {
+    if let Some(parent) = Path::new(path).parent() {
+        create_dir_all(parent).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| LedgerError::SerializationError(err.to_string()))
+}
+
+/// Writes `value` as a length prefixed Borsh buffer and syncs it.
+pub fn append_record<T: BorshSerialize>(mut file: &File, value: &T) -> Result<(), LedgerError> {
+    let value_bytes =
+        to_vec(value).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    let buff =
+        to_vec(&value_bytes).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+
+    file.write_all(&buff)
+        .and_then(|_| file.sync_data())
+        .map_err(|err| LedgerError::SerializationError(err.to_string()))
+}
+
+/// Reads every complete record written by `append_record`, skipping a torn
+/// record at the end of the file. A length prefix over `MAX_RECORD_LEN` is
+/// corruption and fails before anything is allocated for it.
+pub fn read_records<T: BorshDeserialize>(path: &str) -> Result<Vec<T>, LedgerError> {
+    let mut file =
+        File::open(path).map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+    let file_len = file
+        .metadata()
+        .map_err(|err| LedgerError::SerializationError(err.to_string()))?
+        .len();
+
+    let mut records = Vec::new();
+    loop {
+        let mut len_bytes = [0u8; 4];
+        if !read_record(&mut file, &mut len_bytes)? {
+            break;
+        }
+
+        let len = u32::from_le_bytes(len_bytes) as u64;
+        if len > MAX_RECORD_LEN {
+            return Err(LedgerError::SerializationError(format!(
+                "corrupt record of {} bytes in {}, records are at most {} bytes",
+                len, path, MAX_RECORD_LEN
+            )));
+        }
+        let position = file
+            .stream_position()
+            .map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+        if len > file_len.saturating_sub(position) {
+            break;
+        }
+
+        let mut record_bytes = vec![0u8; len as usize];
+        if !read_record(&mut file, &mut record_bytes)? {
+            break;
+        }
+        let record = T::try_from_slice(&record_bytes)
+            .map_err(|err| LedgerError::SerializationError(err.to_string()))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+fn read_record(file: &mut File, buff: &mut [u8]) -> Result<bool, LedgerError> {
+    match file.read_exact(buff) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(LedgerError::SerializationError(err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn test_read_records_torn_and_corrupt() {
+        let dir = std::env::temp_dir().join(format!("journal-test-{}", Keypair::new().pubkey()));
+        let path = dir.join("ledger.journal").to_string_lossy().to_string();
+
+        let file = open_append(&path).unwrap();
+        append_record(&file, &JournalEntry::AdvanceEpoch).unwrap();
+        append_record(&file, &JournalEntry::Snapshot).unwrap();
+        // a torn record claims more bytes than the file holds
+        (&file).write_all(&100u32.to_le_bytes()).unwrap();
+        (&file).write_all(&[1, 2]).unwrap();
+        let entries = Journal::read_entries(&path).unwrap();
+        assert!(matches!(
+            entries[..],
+            [JournalEntry::AdvanceEpoch, JournalEntry::Snapshot]
+        ));
+
+        std::fs::write(&path, u32::MAX.to_le_bytes()).unwrap();
+        let err = Journal::read_entries(&path).unwrap_err();
+        assert!(matches!(err, LedgerError::SerializationError(_)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,24 +1,156 @@
-use borsh::{BorshDeserialize, to_vec};
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
 use std::{
-    fs::{File, create_dir_all},
-    io::{Read, Write},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::File,
+    io::Read,
 };
 
 use crate::pkg::{
-    account::{Account, AccountType},
+    account::{Account, AccountKind, AccountType},
+    block::{Block, BlockStore},
     errors::LedgerError,
+    faucet::{Faucet, FaucetPolicy},
+    fee::FeeSchedule,
+    format,
+    journal::{Journal, JournalEntry},
+    merkle::{AccountProof, hash_leaf, merkle_proof, merkle_root},
+    names::{AccountRef, NameRegistry},
+    processor::{
+        AccountClosure, TransactionContext, charge_fee, checked_add, credit, debit,
+        process_instruction,
+    },
+    query::AccountQuery,
+    rent::{Rent, RentCollection},
+    stake::StakeActivation,
+    storage,
+    supply::SupplyReport,
+    transaction::{Instruction, Transaction},
+    verify::{VerifyReport, verify_accounts},
 };
 
 #[derive(Debug)]
 pub struct Ledger {
-    accounts: Vec<Account>,
+    accounts: BTreeMap<Pubkey, Account>,
+    /// secondary index of the account keys of each kind
+    kind_index: HashMap<AccountKind, BTreeSet<Pubkey>>,
+    names: NameRegistry,
+    epoch: u64,
+    rent: Rent,
+    fees: FeeSchedule,
+    faucet: Faucet,
+    /// lamports added to the ledger minus the ones burned since it was
+    /// loaded, what the accounts must hold
+    supply: u128,
+    /// accounts closed by a transaction, oldest first
+    closures: Vec<AccountClosure>,
+    journal: Option<Journal>,
+    blocks: BlockStore,
+    /// changes applied since the last block
+    pending: Vec<JournalEntry>,
+    /// what migrating the loaded file from an older layout dropped
+    migration_warnings: Vec<String>,
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            kind_index: HashMap::new(),
+            names: NameRegistry::new(),
+            epoch: 0,
+            rent: Rent::free(),
+            fees: FeeSchedule::default(),
+            faucet: Faucet::default(),
+            supply: 0,
+            closures: Vec::new(),
+            journal: None,
+            blocks: BlockStore::default(),
+            pending: Vec::new(),
+            migration_warnings: Vec::new(),
+        }
+    }
 }
 
 impl Ledger {
+    /// Creates an empty ledger without rent, see `set_rent`.
     pub fn new() -> Self {
-        Self {
-            accounts: Vec::new(),
+        Self::default()
+    }
+
+    /// Charges rent with the `rent` schedule: new accounts must be rent exempt
+    /// and `collect_rent` takes its due from the others.
+    pub fn set_rent(&mut self, rent: Rent) -> Result<(), LedgerError> {
+        self.record(JournalEntry::SetRent(rent))?;
+        self.rent = rent;
+
+        Ok(())
+    }
+
+    pub fn rent(&self) -> Rent {
+        self.rent
+    }
+
+    /// Charges `fees` to the fee payer of every following transaction.
+    pub fn set_fees(&mut self, fees: FeeSchedule) -> Result<(), LedgerError> {
+        self.record(JournalEntry::SetFees(fees.clone()))?;
+        self.fees = fees;
+
+        Ok(())
+    }
+
+    pub fn fees(&self) -> &FeeSchedule {
+        &self.fees
+    }
+
+    /// Lets `policy` authorize the following airdrops and withdrawals, the
+    /// usage of the current epoch still counts against its limits. The
+    /// current faucet `authority` must approve the change. A ledger without
+    /// one only takes its first policy while it holds no account, see
+    /// `init_faucet_policy`.
+    pub fn set_faucet_policy(
+        &mut self,
+        authority: &Keypair,
+        policy: FaucetPolicy,
+    ) -> Result<(), LedgerError> {
+        if self.faucet.policy.authority.is_none() {
+            return self.init_faucet_policy(policy);
+        }
+        self.faucet.check_authority(&authority.pubkey())?;
+
+        self.apply_faucet_policy(policy)
+    }
+
+    /// Gives a ledger without faucet authority its first policy, as it is
+    /// created or imported and before it holds any account.
+    pub fn init_faucet_policy(&mut self, policy: FaucetPolicy) -> Result<(), LedgerError> {
+        if self.faucet.policy.authority.is_some() || !self.accounts.is_empty() {
+            return Err(LedgerError::InvalidAuthority(String::from(
+                "the faucet authority can only be configured when the ledger is created",
+            )));
         }
+
+        self.apply_faucet_policy(policy)
+    }
+
+    fn apply_faucet_policy(&mut self, policy: FaucetPolicy) -> Result<(), LedgerError> {
+        self.record(JournalEntry::SetFaucetPolicy(policy.clone()))?;
+        self.faucet.policy = policy;
+
+        Ok(())
+    }
+
+    pub fn faucet(&self) -> &Faucet {
+        &self.faucet
+    }
+
+    /// Fields cleared while migrating the loaded file, they are gone for good
+    /// once the ledger is saved again.
+    pub fn migration_warnings(&self) -> &[String] {
+        &self.migration_warnings
     }
 
     pub fn load_ledger(path: &str) -> Result<Ledger, LedgerError> {
@@ -32,136 +164,791 @@ impl Ledger {
             return Err(LedgerError::SerializationError(err.to_string()));
         };
 
-        let accounts: Result<Vec<Account>, std::io::Error> = Vec::<Account>::try_from_slice(&buff);
-        if let Err(err) = accounts {
-            return Err(LedgerError::SerializationError(err.to_string()));
+        let ledger_file = format::decode(&buff)?;
+        let mut ledger = Ledger {
+            epoch: ledger_file.epoch,
+            rent: ledger_file.rent,
+            names: ledger_file.names,
+            faucet: ledger_file.faucet,
+            closures: ledger_file.closures,
+            fees: ledger_file.fees,
+            migration_warnings: ledger_file.warnings,
+            ..Default::default()
+        };
+        for account in ledger_file.accounts {
+            ledger.insert_account(account.pubkey, account);
         }
+        ledger.supply = ledger.supply_report().total;
 
-        Ok(Ledger {
-            accounts: accounts.unwrap(),
-        })
+        Ok(ledger)
+    }
+
+    /// Builds a ledger holding `accounts`, used when importing them from
+    /// other formats.
+    pub fn from_accounts(epoch: u64, accounts: Vec<Account>) -> Result<Ledger, LedgerError> {
+        let mut ledger = Ledger {
+            epoch,
+            ..Default::default()
+        };
+        for account in accounts {
+            if ledger.accounts.contains_key(&account.pubkey) {
+                return Err(LedgerError::DuplicateAccount(account.pubkey.to_string()));
+            }
+            ledger.insert_account(account.pubkey, account);
+        }
+        ledger.supply = ledger.supply_report().total;
+
+        Ok(ledger)
+    }
+
+    /// Same as `from_accounts` for a whole exported ledger, with its names,
+    /// faucet policy and fee and rent schedules.
+    pub fn from_export(
+        epoch: u64,
+        accounts: Vec<Account>,
+        names: &[(String, Pubkey)],
+        faucet: FaucetPolicy,
+        fees: FeeSchedule,
+        rent: Rent,
+    ) -> Result<Ledger, LedgerError> {
+        let mut ledger = Ledger::from_accounts(epoch, accounts)?;
+        for (name, pubkey) in names {
+            ledger.register_name(name, pubkey)?;
+        }
+        ledger.faucet.policy = faucet;
+        ledger.fees = fees;
+        ledger.rent = rent;
+
+        Ok(ledger)
     }
 
+    /// Atomically replaces the ledger file at `path`, a crash mid-save leaves
+    /// the previous file intact.
     pub fn save_ledger(&self, path: &str) -> Result<(), LedgerError> {
-        let last_index = path.rfind("/").unwrap_or(0);
-        if let Err(err) = create_dir_all(path.get(0..=last_index).unwrap_or("")) {
-            return Err(LedgerError::SerializationError(err.to_string()));
+        self.save_ledger_with_backups(path, 0)
+    }
+
+    /// Same as `save_ledger`, keeping the `keep` previous files next to `path`
+    /// to roll back to, see `storage::backup_path`.
+    pub fn save_ledger_with_backups(&self, path: &str, keep: usize) -> Result<(), LedgerError> {
+        let buff = format::encode(
+            self.epoch,
+            self.rent,
+            &self.accounts.values().collect::<Vec<&Account>>(),
+            &self.names,
+            &self.faucet,
+            &self.closures,
+            &self.fees,
+        )?;
+        storage::write_atomic(path, &buff, keep)?;
+
+        self.journal_append(&JournalEntry::Snapshot)
+    }
+
+    /// Records every following mutation in the journal at `path`, appending to
+    /// it when it already exists.
+    pub fn open_journal(&mut self, path: &str) -> Result<(), LedgerError> {
+        self.journal = Some(Journal::open(path)?);
+        Ok(())
+    }
+
+    /// Loads the blocks stored at `path`, the next produced blocks are
+    /// appended to it. A ledger already holding state when its store is empty
+    /// starts the chain with entries recreating that state.
+    pub fn open_block_store(&mut self, path: &str) -> Result<(), LedgerError> {
+        self.blocks = BlockStore::open(path)?;
+        if self.blocks.blocks().is_empty() {
+            let mut entries = self.genesis_entries();
+            entries.append(&mut self.pending);
+            self.pending = entries;
         }
 
-        let buff = to_vec(&self.accounts);
-        if let Err(err) = &buff {
-            return Err(LedgerError::SerializationError(err.to_string()));
+        Ok(())
+    }
+
+    /// Entries that rebuild the current state from an empty ledger. Accounts
+    /// come first, the rent schedule they were checked against may have
+    /// changed since they were added.
+    fn genesis_entries(&self) -> Vec<JournalEntry> {
+        let mut entries: Vec<JournalEntry> = self
+            .accounts
+            .values()
+            .cloned()
+            .map(JournalEntry::AddAccount)
+            .collect();
+        entries.extend(
+            self.names
+                .iter()
+                .map(|(name, pubkey)| JournalEntry::RegisterName {
+                    name: name.to_string(),
+                    pubkey: *pubkey,
+                }),
+        );
+        entries.extend((0..self.epoch).map(|_| JournalEntry::AdvanceEpoch));
+        if self.rent != Rent::free() {
+            entries.push(JournalEntry::SetRent(self.rent));
+        }
+        if self.fees != FeeSchedule::default() {
+            entries.push(JournalEntry::SetFees(self.fees.clone()));
+        }
+        if self.faucet.policy.authority.is_some() {
+            entries.push(JournalEntry::SetFaucetPolicy(self.faucet.policy.clone()));
         }
 
-        let mut file = File::create(path).unwrap();
-        if let Err(err) = file.write_all(&buff.unwrap()) {
-            return Err(LedgerError::SerializationError(err.to_string()));
+        entries
+    }
+
+    /// Closes the current slot: the changes applied since the previous block
+    /// are batched into a new block holding the resulting state hash.
+    pub fn produce_block(&mut self) -> Result<&Block, LedgerError> {
+        let block = Block::new(
+            self.blocks.last(),
+            std::mem::take(&mut self.pending),
+            self.state_root()?,
+        )?;
+
+        self.blocks.append(block)
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        self.blocks.blocks()
+    }
+
+    /// Verifies the block chain and that the ledger is in the state recorded
+    /// by the last block, run it on a ledger saved right after a block. The
+    /// entries of every block are replayed from an empty ledger to check the
+    /// state hash it holds.
+    pub fn verify_blocks(&self) -> Result<(), LedgerError> {
+        self.blocks.verify(self.state_root()?)?;
+
+        let mut replayed = Ledger::new();
+        for block in self.blocks() {
+            let invalid = |reason: String| LedgerError::InvalidBlock {
+                slot: block.slot,
+                reason,
+            };
+            replayed
+                .apply_entries(block.entries.clone())
+                .map_err(|err| invalid(err.to_string()))?;
+            if replayed.state_root()? != block.state_hash {
+                return Err(invalid(String::from(
+                    "state hash does not match the block entries",
+                )));
+            }
         }
 
         Ok(())
     }
 
+    /// Merkle root over the Borsh encoding of every account, in pubkey order.
+    pub fn state_root(&self) -> Result<Hash, LedgerError> {
+        Ok(merkle_root(&self.account_leaves()?))
+    }
+
+    /// Proof that the account at `pubkey`, as it is now, is part of
+    /// `state_root`.
+    pub fn account_proof(
+        &self,
+        account: impl Into<AccountRef>,
+    ) -> Result<AccountProof, LedgerError> {
+        let pubkey = self.resolve(account)?;
+        let account = self
+            .accounts
+            .get(&pubkey)
+            .ok_or_else(|| LedgerError::AccountNotFound(pubkey.to_string()))?;
+        let index = self.accounts.range(..pubkey).count();
+        let proof = merkle_proof(&self.account_leaves()?, index)
+            .ok_or_else(|| LedgerError::AccountNotFound(pubkey.to_string()))?;
+
+        Ok(AccountProof {
+            account: account.clone(),
+            proof,
+        })
+    }
+
+    fn account_leaves(&self) -> Result<Vec<Hash>, LedgerError> {
+        let mut leaves = Vec::with_capacity(self.accounts.len());
+        for account in self.accounts.values() {
+            leaves.push(hash_leaf(&account.save_to_bytes()?));
+        }
+
+        Ok(leaves)
+    }
+
+    /// Rebuilds a ledger from genesis by applying every entry of the journal,
+    /// the returned ledger keeps appending to the same journal.
+    pub fn replay(journal_path: &str) -> Result<Ledger, LedgerError> {
+        let mut ledger = Ledger::new();
+        ledger.apply_entries(Journal::read_entries(journal_path)?)?;
+        ledger.open_journal(journal_path)?;
+
+        Ok(ledger)
+    }
+
+    /// Loads the snapshot at `snapshot_path` and applies the journal entries
+    /// recorded after the last save.
+    pub fn replay_from_snapshot(
+        snapshot_path: &str,
+        journal_path: &str,
+    ) -> Result<Ledger, LedgerError> {
+        let mut entries = Journal::read_entries(journal_path)?;
+        if let Some(last_snapshot) = entries
+            .iter()
+            .rposition(|entry| matches!(entry, JournalEntry::Snapshot))
+        {
+            entries.drain(..=last_snapshot);
+        }
+
+        let mut ledger = Ledger::load_ledger(snapshot_path)?;
+        ledger.apply_entries(entries)?;
+        ledger.open_journal(journal_path)?;
+
+        Ok(ledger)
+    }
+
+    fn apply_entries(&mut self, entries: Vec<JournalEntry>) -> Result<(), LedgerError> {
+        for entry in entries {
+            match entry {
+                JournalEntry::AddAccount(account) => {
+                    self.add_account(account)?;
+                }
+                JournalEntry::Transaction(tx) => self.process_transaction(&tx)?,
+                JournalEntry::AdvanceEpoch => {
+                    self.advance_epoch()?;
+                }
+                JournalEntry::Snapshot => {}
+                JournalEntry::CollectRent(rent) => {
+                    self.collect_rent_with(rent)?;
+                }
+                JournalEntry::SetFees(fees) => self.set_fees(fees)?,
+                JournalEntry::SetRent(rent) => self.set_rent(rent)?,
+                JournalEntry::RegisterName { name, pubkey } => {
+                    self.register_name(&name, &pubkey)?
+                }
+                JournalEntry::RenameAccount { from, to } => {
+                    self.rename_account(&from, &to)?;
+                }
+                JournalEntry::SetFaucetPolicy(policy) => self.apply_faucet_policy(policy)?,
+                JournalEntry::Airdrop {
+                    authority,
+                    to,
+                    amount,
+                } => {
+                    self.apply_airdrop(&authority, &to, amount)?;
+                }
+                JournalEntry::Withdraw {
+                    authority,
+                    from,
+                    amount,
+                } => {
+                    self.apply_withdraw(&authority, &from, amount)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn journal_append(&self, entry: &JournalEntry) -> Result<(), LedgerError> {
+        match &self.journal {
+            Some(journal) => journal.append(entry),
+            None => Ok(()),
+        }
+    }
+
+    /// Journals a change of the ledger state, the next produced block holds
+    /// it.
+    fn record(&mut self, entry: JournalEntry) -> Result<(), LedgerError> {
+        self.journal_append(&entry)?;
+        self.pending.push(entry);
+
+        Ok(())
+    }
+
+    /// Adds an account as part of the ledger setup, like a genesis account it
+    /// needs no signature and its lamports add to the supply. Use
+    /// `create_account` to go through signature checks with a funded payer.
     pub fn add_account(&mut self, acc: Account) -> Result<&Account, LedgerError> {
-        let pubkey = &acc.pubkey.clone();
-        if self.account_exist(pubkey) {
+        let pubkey = acc.pubkey;
+        if self.accounts.contains_key(&pubkey) {
             return Err(LedgerError::DuplicateAccount(pubkey.to_string()));
         }
+        self.rent.check_exempt(&acc)?;
 
-        self.accounts.push(acc);
-        Ok(self.accounts.iter().find(|a| &a.pubkey == pubkey).unwrap())
+        self.record(JournalEntry::AddAccount(acc.clone()))?;
+        self.supply += acc.lamports as u128;
+        self.insert_account(pubkey, acc);
+        Ok(&self.accounts[&pubkey])
     }
 
-    pub fn accounts_by_type(&self, type_name: &str) -> Vec<&Account> {
-        self.accounts
-            .iter()
-            .filter(|acc| match type_name {
-                "wallet" => acc.is_account_type(AccountType::Wallet { balance: 0 }),
-                "program" => acc.is_account_type(AccountType::Program {
-                    executable: false,
-                    program_data: Vec::new(),
-                }),
-                "token_account" => acc.is_account_type(AccountType::TokenAccount {
-                    mint: "".to_string(),
-                    token_balance: 0,
-                    delegate: None,
-                }),
-                "stake" => acc.is_account_type(AccountType::Stake {
-                    validator: "".to_string(),
-                    staked_amount: 0,
-                }),
-                "all" => true,
-                _ => false,
-            })
-            .collect()
+    /// Same as `add_account`, the account can also be looked up by `name`.
+    pub fn add_named_account(&mut self, name: &str, acc: Account) -> Result<&Account, LedgerError> {
+        self.names.check_available(name)?;
+        let pubkey = acc.pubkey;
+        self.add_account(acc)?;
+        self.register_name(name, &pubkey)?;
+
+        Ok(&self.accounts[&pubkey])
     }
 
-    pub fn transfer(&mut self, from: &str, to: &str, amount: u64) -> Result<(), LedgerError> {
-        if !self.account_exist(&from.to_string()) {
-            return Err(LedgerError::AccountNotFound(from.to_string()));
+    /// Names the account at `pubkey`. Names are case insensitive, they can
+    /// not be empty nor look like a pubkey and each one names one account.
+    pub fn register_name(&mut self, name: &str, pubkey: &Pubkey) -> Result<(), LedgerError> {
+        if !self.accounts.contains_key(pubkey) {
+            return Err(LedgerError::AccountNotFound(pubkey.to_string()));
         }
+        let name = self.names.check_available(name)?;
+
+        self.record(JournalEntry::RegisterName {
+            name: name.clone(),
+            pubkey: *pubkey,
+        })?;
+        self.names.register(&name, *pubkey)
+    }
 
-        if !self.account_exist(&from.to_string()) {
-            return Err(LedgerError::AccountNotFound(to.to_string()));
+    /// Moves the account named `from` to the name `to` and returns its key.
+    pub fn rename_account(&mut self, from: &str, to: &str) -> Result<Pubkey, LedgerError> {
+        if self.names.resolve(from).is_none() {
+            return Err(LedgerError::AccountNotFound(from.to_string()));
         }
+        self.names.check_available(to)?;
+
+        self.record(JournalEntry::RenameAccount {
+            from: from.to_string(),
+            to: to.to_string(),
+        })?;
+        self.names.rename(from, to)
+    }
 
-        let wallets = self.accounts_by_type("wallet");
+    pub fn account_by_name(&self, name: &str) -> Option<&Account> {
+        self.names
+            .resolve(name)
+            .and_then(|pubkey| self.accounts.get(&pubkey))
+    }
 
-        let from_is_wallet = wallets.iter().any(|w| w.pubkey == from);
-        if !from_is_wallet {
+    /// Names of the account at `pubkey` in alphabetical order.
+    pub fn names_of(&self, pubkey: &Pubkey) -> impl Iterator<Item = &str> {
+        self.names.names_of(pubkey)
+    }
+
+    pub fn names(&self) -> &NameRegistry {
+        &self.names
+    }
+
+    /// Key of `account`, given by pubkey or by name. Pubkeys are returned
+    /// as is, whether an account has them or not.
+    pub fn resolve(&self, account: impl Into<AccountRef>) -> Result<Pubkey, LedgerError> {
+        let account = account.into();
+        self.names
+            .resolve_ref(&account)
+            .ok_or_else(|| LedgerError::AccountNotFound(account.to_string()))
+    }
+
+    /// Creates `amount` lamports in the wallet `to` and returns its balance.
+    /// The faucet authority must approve it and the wallet must stay within
+    /// the airdrop limit of the epoch.
+    pub fn airdrop(
+        &mut self,
+        authority: &Keypair,
+        to: impl Into<AccountRef>,
+        amount: u64,
+    ) -> Result<u64, LedgerError> {
+        let to = self.resolve(to)?;
+        self.apply_airdrop(&authority.pubkey(), &to, amount)
+    }
+
+    /// Takes `amount` lamports out of the ledger from the wallet of `from`
+    /// and returns its balance. Both the wallet owner and the faucet
+    /// authority must approve it, within the withdraw limit of the epoch.
+    pub fn withdraw(
+        &mut self,
+        authority: &Keypair,
+        from: &Keypair,
+        amount: u64,
+    ) -> Result<u64, LedgerError> {
+        self.apply_withdraw(&authority.pubkey(), &from.pubkey(), amount)
+    }
+
+    fn apply_airdrop(
+        &mut self,
+        authority: &Pubkey,
+        to: &Pubkey,
+        amount: u64,
+    ) -> Result<u64, LedgerError> {
+        let mut wallet = self.wallet(to, "airdrop to")?;
+        let usage = self
+            .faucet
+            .check_airdrop(authority, to, amount, self.epoch)?;
+        credit(&mut wallet, amount)?;
+
+        self.record(JournalEntry::Airdrop {
+            authority: *authority,
+            to: *to,
+            amount,
+        })?;
+        self.supply += amount as u128;
+        self.faucet.record(*to, usage);
+        let balance = wallet.lamports;
+        self.insert_account(*to, wallet);
+
+        Ok(balance)
+    }
+
+    fn apply_withdraw(
+        &mut self,
+        authority: &Pubkey,
+        from: &Pubkey,
+        amount: u64,
+    ) -> Result<u64, LedgerError> {
+        let mut wallet = self.wallet(from, "withdraw from")?;
+        let usage = self
+            .faucet
+            .check_withdraw(authority, from, amount, self.epoch)?;
+        debit(&mut wallet, amount)?;
+
+        self.record(JournalEntry::Withdraw {
+            authority: *authority,
+            from: *from,
+            amount,
+        })?;
+        self.supply = self.supply.saturating_sub(amount as u128);
+        self.faucet.record(*from, usage);
+        let balance = wallet.lamports;
+        self.insert_account(*from, wallet);
+
+        Ok(balance)
+    }
+
+    /// Copy of the wallet at `pubkey` to `operation` on.
+    fn wallet(&self, pubkey: &Pubkey, operation: &str) -> Result<Account, LedgerError> {
+        let wallet = self
+            .get_account(pubkey)
+            .ok_or_else(|| LedgerError::AccountNotFound(pubkey.to_string()))?;
+        if wallet.kind() != AccountKind::Wallet {
             return Err(LedgerError::InvalidTransfer(format!(
-                "key: {} is not a Wallet",
-                from
+                "{} {} which is not a Wallet",
+                operation, pubkey
             )));
         }
 
-        let to_is_wallet = wallets.iter().any(|w| w.pubkey == to);
-        if !to_is_wallet {
-            return Err(LedgerError::InvalidTransfer(format!(
-                "key: {} is not a Wallet",
-                to
-            )));
+        Ok(wallet.clone())
+    }
+
+    pub fn accounts_by_type(&self, kind: AccountKind) -> Vec<&Account> {
+        self.kind_index
+            .get(&kind)
+            .map(|keys| keys.iter().map(|key| &self.accounts[key]).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = &Account> {
+        self.accounts.values()
+    }
+
+    /// Accounts matching `query`, the names it filters on are resolved
+    /// against the ledger names.
+    pub fn query(&self, query: &AccountQuery) -> Vec<&Account> {
+        let query = query.resolve_names(&self.names);
+        match query.kind_filter() {
+            Some(kind) => query.apply(self.accounts_by_type(kind).into_iter()),
+            None => query.apply(self.accounts.values()),
         }
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<&Account> {
+        self.accounts.get(pubkey)
+    }
+
+    /// Same as `get_account` with the account given by pubkey or by name.
+    pub fn find_account(&self, account: impl Into<AccountRef>) -> Option<&Account> {
+        self.resolve(account)
+            .ok()
+            .and_then(|pubkey| self.accounts.get(&pubkey))
+    }
+
+    /// Transfers to `to` given by pubkey or by name.
+    pub fn transfer(
+        &mut self,
+        from: &Keypair,
+        to: impl Into<AccountRef>,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        let mut tx = Transaction::new(vec![Instruction::Transfer {
+            from: from.pubkey(),
+            to: self.resolve(to)?,
+            amount,
+        }]);
+        tx.sign(from)?;
+
+        self.process_transaction(&tx)
+    }
+
+    /// Creates `account` in a transaction signed by its `keypair`, the
+    /// `payer` wallet pays the fee and funds the lamports of the account.
+    pub fn create_account(
+        &mut self,
+        payer: &Keypair,
+        keypair: &Keypair,
+        account: Account,
+    ) -> Result<(), LedgerError> {
+        let mut tx = Transaction::new(vec![Instruction::CreateAccount { account }])
+            .with_fee_payer(payer.pubkey());
+        tx.sign(keypair)?.sign(payer)?;
+
+        self.process_transaction(&tx)
+    }
+
+    /// Runs the executable `program` over `accounts` with `data` as input.
+    /// `signer` signs and pays for the transaction, the program sees it as a
+    /// signer when it is one of the accounts.
+    pub fn invoke<T: Into<AccountRef>>(
+        &mut self,
+        signer: &Keypair,
+        program: impl Into<AccountRef>,
+        accounts: impl IntoIterator<Item = T>,
+        data: Vec<u8>,
+    ) -> Result<(), LedgerError> {
+        let accounts = accounts
+            .into_iter()
+            .map(|account| self.resolve(account))
+            .collect::<Result<_, _>>()?;
+        let mut tx = Transaction::new(vec![Instruction::Invoke {
+            program: self.resolve(program)?,
+            accounts,
+            signers: vec![signer.pubkey()],
+            data,
+        }]);
+        tx.sign(signer)?;
 
-        let from_wallet = self.accounts.iter_mut().find(|w| w.pubkey == from).unwrap();
-        if from_wallet.lamports < amount {
-            return Err(LedgerError::InsufficientFunds {
-                require: amount,
-                available: from_wallet.lamports,
-            });
+        self.process_transaction(&tx)
+    }
+
+    /// Deletes the account of `owner`, draining its lamports to the wallet
+    /// `destination`. Token accounts holding tokens, mints with a supply and
+    /// stakes that are not cooled down can not be closed. The closure runs as
+    /// a `CloseAccount` transaction signed and paid for by `owner`, it is
+    /// kept in `closures`.
+    pub fn close_account(
+        &mut self,
+        owner: &Keypair,
+        destination: impl Into<AccountRef>,
+    ) -> Result<AccountClosure, LedgerError> {
+        let mut tx = Transaction::new(vec![Instruction::CloseAccount {
+            pubkey: owner.pubkey(),
+            destination: self.resolve(destination)?,
+        }]);
+        tx.sign(owner)?;
+
+        let ctx = self.execute_transaction(&tx)?;
+        let closure = ctx.closures()[0].clone();
+        self.commit_transaction(&tx, ctx)?;
+        Ok(closure)
+    }
+
+    pub fn closures(&self) -> &[AccountClosure] {
+        &self.closures
+    }
+
+    /// Applies every instruction of the transaction or none of them: the
+    /// instructions run against a copy of the accounts they touch, and the
+    /// copy only replaces the ledger accounts once all of them succeeded.
+    /// Transactions missing a signature from any account they debit or
+    /// close are rejected before anything runs. The fee payer is charged the
+    /// fee schedule first, a failed transaction costs nothing. With a journal
+    /// open the transaction is recorded before its changes are committed.
+    pub fn process_transaction(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        let ctx = self.execute_transaction(tx)?;
+        self.commit_transaction(tx, ctx)
+    }
+
+    /// Runs the transaction against a working copy of its accounts without
+    /// touching the ledger, see `process_transaction`.
+    pub(crate) fn execute_transaction(
+        &self,
+        tx: &Transaction,
+    ) -> Result<TransactionContext, LedgerError> {
+        tx.verify_signatures()?;
+
+        let mut ctx = TransactionContext::new(self.epoch, self.rent).with_payer(tx.fee_payer());
+        let collector = self.fees.collector;
+        for pubkey in tx.account_keys().into_iter().chain(collector) {
+            ctx.load(&pubkey, self.get_account(&pubkey).cloned());
         }
 
-        from_wallet.lamports -= amount;
-        if let AccountType::Wallet { ref mut balance } = from_wallet.account_type {
-            *balance -= amount;
+        let fee = self.fees.fee(tx)?;
+        if fee > 0 {
+            let payer = tx.fee_payer().ok_or_else(|| {
+                LedgerError::InvalidTransfer(String::from("transaction has no fee payer"))
+            })?;
+            charge_fee(&mut ctx, &payer, fee, collector.as_ref())?;
         }
 
-        let to_wallet = self.accounts.iter_mut().find(|w| w.pubkey == to).unwrap();
-        to_wallet.lamports += amount;
-        if let AccountType::Wallet { ref mut balance } = to_wallet.account_type {
-            *balance += amount;
+        for instruction in &tx.instructions {
+            process_instruction(&mut ctx, instruction)?;
         }
 
+        Ok(ctx)
+    }
+
+    /// Records the transaction and replaces the accounts it touched with
+    /// their executed copies.
+    pub(crate) fn commit_transaction(
+        &mut self,
+        tx: &Transaction,
+        ctx: TransactionContext,
+    ) -> Result<(), LedgerError> {
+        self.record(JournalEntry::Transaction(tx.clone()))?;
+
+        self.apply_context(ctx);
+
         Ok(())
     }
 
-    pub fn total_supply(&self) -> u64 {
-        self.accounts
-            .iter()
-            .map(|acc| acc.lamports)
-            .reduce(|total, value| total + value)
-            .unwrap_or_default()
+    fn apply_context(&mut self, ctx: TransactionContext) {
+        self.supply = self.supply.saturating_sub(ctx.burned() as u128);
+        self.closures.extend_from_slice(ctx.closures());
+        for (pubkey, account) in ctx.into_accounts() {
+            match account {
+                Some(account) => self.insert_account(pubkey, account),
+                None => self.remove_account(&pubkey),
+            }
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
     }
 
-    fn account_exist(&self, pubkey: &String) -> bool {
+    /// Moves to the next epoch, stake delegations warm up and cool down one
+    /// step per epoch.
+    pub fn advance_epoch(&mut self) -> Result<u64, LedgerError> {
+        self.record(JournalEntry::AdvanceEpoch)?;
+        self.epoch += 1;
+
+        Ok(self.epoch)
+    }
+
+    /// Charges one epoch of rent to every account below the exemption
+    /// minimum, meant to run once per epoch. Accounts left without lamports
+    /// are closed and the collected lamports are burned.
+    pub fn collect_rent(&mut self) -> Result<RentCollection, LedgerError> {
+        self.collect_rent_with(self.rent)
+    }
+
+    fn collect_rent_with(&mut self, rent: Rent) -> Result<RentCollection, LedgerError> {
+        // the total is checked before any account is charged
+        let collected = self
+            .accounts
+            .values()
+            .try_fold(0, |total, account| checked_add(total, rent.charge(account)))?;
+        self.record(JournalEntry::CollectRent(rent))?;
+
+        self.supply = self.supply.saturating_sub(collected as u128);
+        let mut collection = RentCollection {
+            collected,
+            ..Default::default()
+        };
+        let mut closed = Vec::new();
+        for (pubkey, account) in self.accounts.iter_mut() {
+            rent.collect(account);
+            if account.lamports == 0 && !rent.is_exempt(0, account.data_len()) {
+                closed.push(*pubkey);
+            }
+        }
+        for pubkey in closed {
+            self.remove_account(&pubkey);
+            collection.closed.push(pubkey);
+        }
+
+        Ok(collection)
+    }
+
+    pub fn stake_activation(
+        &self,
+        account: impl Into<AccountRef>,
+    ) -> Result<StakeActivation, LedgerError> {
+        let pubkey = self.resolve(account)?;
+        let account = self
+            .get_account(&pubkey)
+            .ok_or_else(|| LedgerError::AccountNotFound(pubkey.to_string()))?;
+        match account.account_type {
+            AccountType::Stake {
+                staked_amount,
+                activation_epoch,
+                deactivation_epoch,
+                ..
+            } => Ok(StakeActivation::at_epoch(
+                staked_amount,
+                activation_epoch,
+                deactivation_epoch,
+                self.epoch,
+            )),
+            _ => Err(LedgerError::InvalidTransfer(format!(
+                "key: {} is not a Stake",
+                pubkey
+            ))),
+        }
+    }
+
+    /// Lamports held by every account, `ArithmeticOverflow` when they do not
+    /// fit a `u64`, see `supply_report`.
+    pub fn total_supply(&self) -> Result<u64, LedgerError> {
         self.accounts
-            .iter()
-            .any(|acc| acc.pubkey == pubkey.to_owned())
+            .values()
+            .try_fold(0, |total, acc| checked_add(total, acc.lamports))
+    }
+
+    /// Checks the ledger invariants: accounts are stored under their own key,
+    /// wallet balances match their lamports, stakes do not delegate more than
+    /// they hold, mint supplies match the tokens of their accounts and the
+    /// lamports held are the ones added minus the ones burned.
+    pub fn verify(&self) -> VerifyReport {
+        verify_accounts(self.accounts.iter(), self.supply)
+    }
+
+    /// Lamport and token totals, wide enough to never overflow.
+    pub fn supply_report(&self) -> SupplyReport {
+        SupplyReport::from_accounts(self.accounts.values())
+    }
+
+    fn insert_account(&mut self, pubkey: Pubkey, account: Account) {
+        self.kind_index
+            .entry(account.kind())
+            .or_default()
+            .insert(pubkey);
+        if let Some(previous) = self.accounts.insert(pubkey, account)
+            && previous.kind() != self.accounts[&pubkey].kind()
+        {
+            self.remove_from_kind_index(previous.kind(), &pubkey);
+        }
+    }
+
+    /// Removes the account and every name of it.
+    fn remove_account(&mut self, pubkey: &Pubkey) {
+        if let Some(account) = self.accounts.remove(pubkey) {
+            self.remove_from_kind_index(account.kind(), pubkey);
+            self.names.remove_key(pubkey);
+        }
+    }
+
+    fn remove_from_kind_index(&mut self, kind: AccountKind, pubkey: &Pubkey) {
+        if let Some(keys) = self.kind_index.get_mut(&kind) {
+            keys.remove(pubkey);
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::pkg::{
+        account::{STAKE_PROGRAM_ID, SYSTEM_PROGRAM_ID},
+        query::AccountOrder,
+        stake::StakeStatus,
+        verify::Violation,
+        vm::op,
+    };
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn ledger_test_add_account() {
@@ -174,7 +961,7 @@ mod test {
         assert!(ledger.accounts.len() == 1);
 
         let err = ledger.add_account(account.clone()).unwrap_err();
-        let expected_err = LedgerError::DuplicateAccount(account.pubkey.clone());
+        let expected_err = LedgerError::DuplicateAccount(account.pubkey.to_string());
         assert_eq!(err.to_string(), expected_err.to_string());
         assert!(ledger.accounts.len() == 1);
 
@@ -200,17 +987,17 @@ mod test {
         handle_add_account(&mut ledger, wallet_1.clone());
         handle_add_account(&mut ledger, program_1.clone());
 
-        let wallets = ledger.accounts_by_type("wallet");
+        let wallets = ledger.accounts_by_type(AccountKind::Wallet);
         assert!(wallets.len() == 1);
         assert_eq!(wallets.first().unwrap().pubkey, wallet_1.pubkey);
 
-        let toke_accounts = ledger.accounts_by_type("toke_account");
-        assert!(toke_accounts.is_empty());
+        let token_accounts = ledger.accounts_by_type(AccountKind::TokenAccount);
+        assert!(token_accounts.is_empty());
 
-        let no_valid_type = ledger.accounts_by_type("hfsdbhfsdbhfds");
-        assert!(no_valid_type.is_empty());
+        assert!("toke_account".parse::<AccountKind>().is_err());
+        assert!("hfsdbhfsdbhfds".parse::<AccountKind>().is_err());
 
-        let programs = ledger.accounts_by_type("program");
+        let programs = ledger.accounts_by_type(AccountKind::Program);
         assert!(programs.len() == 1);
         assert_eq!(programs.first().unwrap().pubkey, program_1.pubkey);
     }
@@ -218,23 +1005,26 @@ mod test {
     #[test]
     fn ledger_test_total_suply() {
         let mut ledger = Ledger::new();
-        assert!(ledger.total_supply() == 0);
+        assert!(ledger.total_supply().unwrap() == 0);
 
         let program_acc = Account::new(AccountType::Program {
             executable: false,
             program_data: vec![],
         });
+        // program does not have balance but they need a minimun of lamports to be rent excempt;
+        let program_lamports = Rent::default().minimum_balance(program_acc.data_len());
+        assert_eq!(program_acc.lamports, program_lamports);
         handle_add_account(&mut ledger, program_acc);
-        assert!(ledger.total_supply() == 1); // program does not have balance but they need a minimun of lamports to be rent excempt;
+        assert!(ledger.total_supply().unwrap() == program_lamports);
 
         let stacked_coins: u64 = 200_000_000_000_000;
         let stake_acc = Account::new(AccountType::TokenAccount {
-            mint: String::new(),
+            mint: Pubkey::default(),
             token_balance: stacked_coins,
             delegate: None,
         });
         handle_add_account(&mut ledger, stake_acc);
-        assert!(ledger.total_supply() == stacked_coins + 1); // we use the amouint of stacked coins as lamports for the account
+        assert!(ledger.total_supply().unwrap() == stacked_coins + program_lamports); // we use the amouint of stacked coins as lamports for the account
 
         let balance_coins: u64 = 40_000_000_000;
         let wallet_acc = Account::new(AccountType::Wallet {
@@ -242,12 +1032,13 @@ mod test {
         });
         handle_add_account(&mut ledger, wallet_acc);
 
-        assert!(ledger.total_supply() == stacked_coins + balance_coins + 1);
+        assert!(ledger.total_supply().unwrap() == stacked_coins + balance_coins + program_lamports);
     }
 
     #[test]
     fn ledger_test_transfer() {
-        let wallet_1 = Account::new(AccountType::Wallet { balance: 10 });
+        let keypair_1 = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 10 });
         let wallet_2 = Account::new(AccountType::Wallet { balance: 2 });
 
         let program_1 = Account::new(AccountType::Program {
@@ -260,7 +1051,7 @@ mod test {
         handle_add_account(&mut ledger, wallet_2.clone());
         handle_add_account(&mut ledger, program_1.clone());
 
-        if let Err(err) = ledger.transfer(&wallet_1.pubkey, &wallet_2.pubkey, 15) {
+        if let Err(err) = ledger.transfer(&keypair_1, wallet_2.pubkey, 15) {
             let expected_err = LedgerError::InsufficientFunds {
                 require: 15,
                 available: 10,
@@ -268,17 +1059,1499 @@ mod test {
             assert_eq!(err.to_string(), expected_err.to_string());
         }
 
-        if let Err(err) = ledger.transfer(&wallet_1.pubkey, &wallet_2.pubkey, 3) {
+        if let Err(err) = ledger.transfer(&keypair_1, wallet_2.pubkey, 3) {
             panic!("{}", err.to_string());
         }
 
-        if let Err(err) = ledger.transfer(&wallet_1.pubkey, &program_1.pubkey, 1) {
+        if let Err(err) = ledger.transfer(&keypair_1, program_1.pubkey, 1) {
             let expected_err =
                 LedgerError::InvalidTransfer(format!("key: {} is not a Wallet", program_1.pubkey));
             assert_eq!(err.to_string(), expected_err.to_string());
         }
     }
 
+    #[test]
+    fn ledger_test_transfer_requires_signature() {
+        let keypair_1 = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 10 });
+        let wallet_2 = Account::new(AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, wallet_1.clone());
+        handle_add_account(&mut ledger, wallet_2.clone());
+
+        let mut tx = Transaction::new(vec![Instruction::Transfer {
+            from: wallet_1.pubkey,
+            to: wallet_2.pubkey,
+            amount: 5,
+        }]);
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        let expected_err = LedgerError::MissingSignature(wallet_1.pubkey.to_string());
+        assert_eq!(err.to_string(), expected_err.to_string());
+
+        let intruder = Keypair::new();
+        tx.sign(&intruder).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        assert_eq!(err.to_string(), expected_err.to_string());
+
+        tx.sign(&keypair_1).unwrap();
+        tx.add_instruction(Instruction::Transfer {
+            from: wallet_1.pubkey,
+            to: wallet_2.pubkey,
+            amount: 5,
+        });
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        let expected_err = LedgerError::InvalidSignature(intruder.pubkey().to_string());
+        assert_eq!(err.to_string(), expected_err.to_string());
+        assert_eq!(ledger.get_account(&wallet_2.pubkey).unwrap().lamports, 0);
+
+        tx.sign(&intruder).unwrap().sign(&keypair_1).unwrap();
+        ledger.process_transaction(&tx).unwrap();
+        assert_eq!(ledger.get_account(&wallet_2.pubkey).unwrap().lamports, 10);
+    }
+
+    #[test]
+    fn ledger_test_transaction_is_atomic() {
+        let keypair_1 = Keypair::new();
+        let keypair_3 = Keypair::new();
+        let new_keypair = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 100 });
+        let wallet_2 = Account::new(AccountType::Wallet { balance: 0 });
+        let wallet_3 = Account::from_keypair(&keypair_3, AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, wallet_1.clone());
+        handle_add_account(&mut ledger, wallet_2.clone());
+        handle_add_account(&mut ledger, wallet_3.clone());
+
+        let mut tx = Transaction::new(vec![
+            Instruction::Transfer {
+                from: wallet_1.pubkey,
+                to: wallet_2.pubkey,
+                amount: 60,
+            },
+            Instruction::Transfer {
+                from: wallet_1.pubkey,
+                to: wallet_3.pubkey,
+                amount: 60,
+            },
+        ]);
+        tx.sign(&keypair_1).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        let expected_err = LedgerError::InsufficientFunds {
+            require: 60,
+            available: 40,
+        };
+        assert_eq!(err.to_string(), expected_err.to_string());
+        assert_eq!(ledger.get_account(&wallet_1.pubkey).unwrap().lamports, 100);
+        assert_eq!(ledger.get_account(&wallet_2.pubkey).unwrap().lamports, 0);
+        assert_eq!(ledger.get_account(&wallet_3.pubkey).unwrap().lamports, 0);
+
+        let new_wallet = Account::from_keypair(&new_keypair, AccountType::Wallet { balance: 0 });
+        let mut tx = Transaction::new(vec![
+            Instruction::CreateAccount {
+                account: new_wallet.clone(),
+            },
+            Instruction::Transfer {
+                from: wallet_1.pubkey,
+                to: new_wallet.pubkey,
+                amount: 30,
+            },
+            Instruction::CloseAccount {
+                pubkey: wallet_3.pubkey,
+                destination: wallet_2.pubkey,
+            },
+            Instruction::Transfer {
+                from: wallet_1.pubkey,
+                to: wallet_3.pubkey,
+                amount: 1,
+            },
+        ]);
+        tx.sign(&keypair_1)
+            .unwrap()
+            .sign(&keypair_3)
+            .unwrap()
+            .sign(&new_keypair)
+            .unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        let expected_err = LedgerError::AccountNotFound(wallet_3.pubkey.to_string());
+        assert_eq!(err.to_string(), expected_err.to_string());
+        assert!(ledger.get_account(&new_wallet.pubkey).is_none());
+        assert!(ledger.get_account(&wallet_3.pubkey).is_some());
+        assert_eq!(ledger.total_supply().unwrap(), 100);
+
+        tx.instructions.pop();
+        tx.sign(&keypair_1)
+            .unwrap()
+            .sign(&keypair_3)
+            .unwrap()
+            .sign(&new_keypair)
+            .unwrap();
+        ledger.process_transaction(&tx).unwrap();
+        assert_eq!(ledger.get_account(&new_wallet.pubkey).unwrap().lamports, 30);
+        assert_eq!(ledger.get_account(&wallet_1.pubkey).unwrap().lamports, 70);
+        assert!(ledger.get_account(&wallet_3.pubkey).is_none());
+        assert_eq!(ledger.total_supply().unwrap(), 100);
+    }
+
+    #[test]
+    fn ledger_test_token_transfer() {
+        let mint = Pubkey::new_unique();
+        let keypair_1 = Keypair::new();
+        let token_1 = Account::from_keypair(
+            &keypair_1,
+            AccountType::TokenAccount {
+                mint,
+                token_balance: 50,
+                delegate: None,
+            },
+        );
+        let token_2 = Account::new(AccountType::TokenAccount {
+            mint,
+            token_balance: 0,
+            delegate: None,
+        });
+        let other_mint = Account::new(AccountType::TokenAccount {
+            mint: Pubkey::new_unique(),
+            token_balance: 0,
+            delegate: None,
+        });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, token_1.clone());
+        handle_add_account(&mut ledger, token_2.clone());
+        handle_add_account(&mut ledger, other_mint.clone());
+
+        let mut tx = Transaction::new(vec![Instruction::TokenTransfer {
+            from: token_1.pubkey,
+            to: other_mint.pubkey,
+            authority: token_1.pubkey,
+            amount: 10,
+        }]);
+        tx.sign(&keypair_1).unwrap();
+        assert!(ledger.process_transaction(&tx).is_err());
+
+        let mut tx = Transaction::new(vec![Instruction::TokenTransfer {
+            from: token_1.pubkey,
+            to: token_2.pubkey,
+            authority: token_1.pubkey,
+            amount: 10,
+        }]);
+        tx.sign(&keypair_1).unwrap();
+        ledger.process_transaction(&tx).unwrap();
+        if let AccountType::TokenAccount { token_balance, .. } =
+            ledger.get_account(&token_2.pubkey).unwrap().account_type
+        {
+            assert_eq!(token_balance, 10);
+        } else {
+            panic!("account is not a token account");
+        }
+    }
+
+    #[test]
+    fn ledger_test_journal_replay() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+        let snapshot_path = dir.path("ledger.bin");
+
+        let keypair_1 = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 100 });
+        let wallet_2 = Account::new(AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        handle_add_account(&mut ledger, wallet_1.clone());
+        handle_add_account(&mut ledger, wallet_2.clone());
+        ledger.transfer(&keypair_1, wallet_2.pubkey, 30).unwrap();
+        ledger.save_ledger(&snapshot_path).unwrap();
+        ledger.transfer(&keypair_1, wallet_2.pubkey, 20).unwrap();
+        assert!(ledger.transfer(&keypair_1, wallet_2.pubkey, 500).is_err());
+
+        let entries = Journal::read_entries(&journal_path).unwrap();
+        assert_eq!(entries.len(), 5);
+
+        let replayed = Ledger::replay(&journal_path).unwrap();
+        let from_snapshot = Ledger::replay_from_snapshot(&snapshot_path, &journal_path).unwrap();
+        for restored in [&replayed, &from_snapshot] {
+            assert_eq!(restored.accounts.len(), 2);
+            assert_eq!(restored.get_account(&wallet_1.pubkey).unwrap().lamports, 50);
+            assert_eq!(restored.get_account(&wallet_2.pubkey).unwrap().lamports, 50);
+        }
+    }
+
+    #[test]
+    fn ledger_test_save_backups() {
+        let dir = TestDir::new();
+        let path = dir.path("ledger.bin");
+
+        let mut ledger = Ledger::new();
+        for balance in 1..=4 {
+            handle_add_account(&mut ledger, Account::new(AccountType::Wallet { balance }));
+            ledger.save_ledger_with_backups(&path, 2).unwrap();
+        }
+
+        assert_eq!(Ledger::load_ledger(&path).unwrap().accounts.len(), 4);
+        assert_eq!(
+            Ledger::load_ledger(&storage::backup_path(&path, 1))
+                .unwrap()
+                .accounts
+                .len(),
+            3
+        );
+        assert_eq!(
+            Ledger::load_ledger(&storage::backup_path(&path, 2))
+                .unwrap()
+                .accounts
+                .len(),
+            2
+        );
+        assert!(!Path::new(&storage::backup_path(&path, 3)).exists());
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+    }
+
+    #[test]
+    fn ledger_test_rent() {
+        let rent = Rent::default();
+        let keypair_1 = Keypair::new();
+        let wallet_1 = Account::from_keypair(
+            &keypair_1,
+            AccountType::Wallet {
+                balance: 10_000_000,
+            },
+        );
+        let minimum = rent.minimum_balance(wallet_1.data_len());
+        let poor_wallet = Account::new(AccountType::Wallet {
+            balance: minimum - 1,
+        });
+
+        let mut ledger = Ledger::new();
+        ledger.set_rent(rent).unwrap();
+        let err = ledger.add_account(poor_wallet.clone()).unwrap_err();
+        let expected_err = LedgerError::RentNotExempt {
+            pubkey: poor_wallet.pubkey.to_string(),
+            minimum,
+            lamports: minimum - 1,
+        };
+        assert_eq!(err.to_string(), expected_err.to_string());
+        handle_add_account(&mut ledger, wallet_1.clone());
+
+        let program_keypair = Keypair::new();
+        let program = Account::from_keypair(
+            &program_keypair,
+            AccountType::Program {
+                executable: true,
+                program_data: vec![7; 1_000],
+            },
+        );
+        let create = Transaction::new(vec![Instruction::CreateAccount {
+            account: program.clone(),
+        }]);
+        let mut tx = create.clone();
+        tx.sign(&program_keypair).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+
+        // the fee payer funds the exemption minimum of the program
+        let funder_keypair = Keypair::new();
+        let funder = Account::from_keypair(
+            &funder_keypair,
+            AccountType::Wallet {
+                balance: program.lamports + minimum,
+            },
+        );
+        handle_add_account(&mut ledger, funder.clone());
+        let mut tx = create.with_fee_payer(funder.pubkey);
+        tx.sign(&program_keypair)
+            .unwrap()
+            .sign(&funder_keypair)
+            .unwrap();
+        ledger.process_transaction(&tx).unwrap();
+        assert!(rent.is_exempt(program.lamports, program.data_len()));
+        assert_eq!(
+            ledger.get_account(&funder.pubkey).unwrap().lamports,
+            minimum
+        );
+
+        // a transfer drains the wallet below the exemption minimum
+        let wallet_2 = Account::new(AccountType::Wallet { balance: minimum });
+        handle_add_account(&mut ledger, wallet_2.clone());
+        let due = rent.due(wallet_1.data_len());
+        ledger
+            .transfer(&keypair_1, wallet_2.pubkey, 10_000_000 - due - 1)
+            .unwrap();
+
+        let supply = ledger.total_supply().unwrap();
+        let collection = ledger.collect_rent().unwrap();
+        assert_eq!(collection.collected, due);
+        assert!(collection.closed.is_empty());
+        assert_eq!(ledger.total_supply().unwrap(), supply - due);
+        assert_eq!(ledger.get_account(&wallet_1.pubkey).unwrap().lamports, 1);
+
+        let collection = ledger.collect_rent().unwrap();
+        assert_eq!(collection.collected, 1);
+        assert_eq!(collection.closed, vec![wallet_1.pubkey]);
+        assert!(ledger.get_account(&wallet_1.pubkey).is_none());
+        assert!(ledger.get_account(&wallet_2.pubkey).is_some());
+    }
+
+    #[test]
+    fn ledger_test_rent_schedule_is_kept() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+        let snapshot_path = dir.path("ledger.bin");
+        let rent = Rent {
+            lamports_per_byte_year: 10,
+            exemption_threshold_years: 2,
+            epochs_per_year: 4,
+        };
+
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        ledger.set_rent(rent).unwrap();
+        // new mints are funded for the rent schedule of the ledger
+        let mint = Account::new(AccountType::Mint {
+            decimals: 9,
+            supply: 0,
+            mint_authority: None,
+            freeze_authority: None,
+        });
+        let mint = mint.with_rent(&rent);
+        assert_eq!(mint.lamports, rent.minimum_balance(mint.data_len()));
+        assert_eq!(mint.clone().with_rent(&Rent::free()).lamports, 0);
+        handle_add_account(&mut ledger, mint);
+        ledger.save_ledger(&snapshot_path).unwrap();
+
+        assert_eq!(Ledger::load_ledger(&snapshot_path).unwrap().rent(), rent);
+        assert_eq!(Ledger::replay(&journal_path).unwrap().rent(), rent);
+    }
+
+    #[test]
+    fn ledger_test_fees() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+        let snapshot_path = dir.path("ledger.bin");
+
+        let keypair_1 = Keypair::new();
+        let payer_keypair = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 1_000 });
+        let payer = Account::from_keypair(&payer_keypair, AccountType::Wallet { balance: 100 });
+        let wallet_2 = Account::new(AccountType::Wallet { balance: 0 });
+        let collector = Account::new(AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        for account in [&wallet_1, &payer, &wallet_2, &collector] {
+            handle_add_account(&mut ledger, account.clone());
+        }
+
+        // fees are burned without a collector
+        ledger.set_fees(FeeSchedule::new(5, 2)).unwrap();
+        ledger.transfer(&keypair_1, wallet_2.pubkey, 100).unwrap();
+        assert_eq!(ledger.get_account(&wallet_1.pubkey).unwrap().lamports, 893);
+        assert_eq!(ledger.total_supply().unwrap(), 1_093);
+
+        ledger
+            .set_fees(FeeSchedule::new(5, 2).with_collector(collector.pubkey))
+            .unwrap();
+        let mut tx = Transaction::new(vec![Instruction::Transfer {
+            from: wallet_1.pubkey,
+            to: wallet_2.pubkey,
+            amount: 100,
+        }])
+        .with_fee_payer(payer.pubkey)
+        .with_priority_fee(10);
+        assert!(ledger.process_transaction(&tx).is_err());
+        tx.sign(&keypair_1).unwrap().sign(&payer_keypair).unwrap();
+        assert_eq!(ledger.fees().fee(&tx).unwrap(), 22);
+        ledger.process_transaction(&tx).unwrap();
+        assert_eq!(ledger.get_account(&wallet_1.pubkey).unwrap().lamports, 793);
+        assert_eq!(ledger.get_account(&payer.pubkey).unwrap().lamports, 78);
+        assert_eq!(ledger.get_account(&collector.pubkey).unwrap().lamports, 22);
+        assert_eq!(ledger.total_supply().unwrap(), 1_093);
+
+        // the payer can not cover the fee, nothing is applied
+        let mut tx = Transaction::new(vec![Instruction::Transfer {
+            from: payer.pubkey,
+            to: wallet_2.pubkey,
+            amount: 1,
+        }])
+        .with_priority_fee(1_000);
+        tx.sign(&payer_keypair).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        let expected_err = LedgerError::InsufficientFunds {
+            require: 1_007,
+            available: 78,
+        };
+        assert_eq!(err.to_string(), expected_err.to_string());
+        assert_eq!(ledger.get_account(&payer.pubkey).unwrap().lamports, 78);
+
+        // the signer pays without an explicit fee payer, even when the
+        // instructions do not touch its account
+        let program = Account::new(AccountType::Program {
+            executable: true,
+            program_data: vec![],
+        });
+        handle_add_account(&mut ledger, program.clone());
+        ledger
+            .invoke(&payer_keypair, program.pubkey, Vec::<Pubkey>::new(), vec![])
+            .unwrap();
+        assert_eq!(ledger.get_account(&payer.pubkey).unwrap().lamports, 71);
+        assert_eq!(ledger.get_account(&collector.pubkey).unwrap().lamports, 29);
+
+        // the fee schedule is saved with the ledger
+        ledger.save_ledger(&snapshot_path).unwrap();
+        assert_eq!(
+            Ledger::load_ledger(&snapshot_path).unwrap().fees(),
+            ledger.fees()
+        );
+
+        let replayed = Ledger::replay(&journal_path).unwrap();
+        assert_eq!(replayed.fees(), ledger.fees());
+        for account in ledger.accounts() {
+            assert_eq!(
+                replayed.get_account(&account.pubkey).unwrap().lamports,
+                account.lamports
+            );
+        }
+    }
+
+    #[test]
+    fn ledger_test_blocks() {
+        let dir = TestDir::new();
+        let blocks_path = dir.path("ledger.blocks");
+
+        let keypair_1 = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 100 });
+        let wallet_2 = Account::new(AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        ledger.open_block_store(&blocks_path).unwrap();
+        handle_add_account(&mut ledger, wallet_1.clone());
+        handle_add_account(&mut ledger, wallet_2.clone());
+        let genesis = ledger.produce_block().unwrap().clone();
+        assert_eq!(genesis.slot, 0);
+        assert_eq!(genesis.parent_hash, Hash::default());
+
+        ledger.transfer(&keypair_1, wallet_2.pubkey, 10).unwrap();
+        ledger.transfer(&keypair_1, wallet_2.pubkey, 20).unwrap();
+        let block = ledger.produce_block().unwrap().clone();
+        assert_eq!(block.slot, 1);
+        assert_eq!(block.parent_hash, genesis.hash());
+        assert_eq!(block.entries.len(), 2);
+        assert_eq!(block.state_hash, ledger.state_root().unwrap());
+        ledger.verify_blocks().unwrap();
+
+        // the chain is read back from the block store
+        let mut reloaded = Ledger::new();
+        handle_add_account(&mut reloaded, wallet_1.clone());
+        reloaded.open_block_store(&blocks_path).unwrap();
+        assert_eq!(reloaded.blocks().len(), 2);
+        assert!(matches!(
+            reloaded.verify_blocks().unwrap_err(),
+            LedgerError::InvalidBlock { slot: 1, .. }
+        ));
+
+        let mut tampered = BlockStore::default();
+        tampered.append(genesis.clone()).unwrap();
+        let mut forged = block.clone();
+        forged.entries.pop();
+        tampered.append(forged).unwrap();
+        assert!(matches!(
+            tampered.verify(block.state_hash).unwrap_err(),
+            LedgerError::InvalidBlock { slot: 1, .. }
+        ));
+
+        // a rehashed chain whose genesis entries do not lead to its state
+        let mut entries = genesis.entries.clone();
+        entries.pop();
+        let mut rehashed = BlockStore::default();
+        rehashed
+            .append(Block::new(None, entries, genesis.state_hash).unwrap())
+            .unwrap();
+        let forged = Block::new(rehashed.last(), block.entries.clone(), block.state_hash);
+        rehashed.append(forged.unwrap()).unwrap();
+        ledger.blocks = rehashed;
+        assert!(matches!(
+            ledger.verify_blocks().unwrap_err(),
+            LedgerError::InvalidBlock { slot: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn ledger_test_blocks_replay_every_change() {
+        let dir = TestDir::new();
+        let snapshot_path = dir.path("ledger.bin");
+        let blocks_path = dir.path("ledger.blocks");
+        let authority = Keypair::new();
+        let keypair = Keypair::new();
+        let wallet = Account::from_keypair(&keypair, AccountType::Wallet { balance: 0 });
+
+        // a ledger saved before it produced any block
+        let mut ledger = Ledger::new();
+        ledger
+            .set_faucet_policy(&authority, FaucetPolicy::new(authority.pubkey()))
+            .unwrap();
+        ledger.add_named_account("alice", wallet.clone()).unwrap();
+        ledger.advance_epoch().unwrap();
+        ledger.save_ledger(&snapshot_path).unwrap();
+
+        let mut ledger = Ledger::load_ledger(&snapshot_path).unwrap();
+        ledger.open_block_store(&blocks_path).unwrap();
+        ledger.airdrop(&authority, "alice", 50).unwrap();
+        let genesis = ledger.produce_block().unwrap();
+        assert!(matches!(
+            genesis.entries.last(),
+            Some(JournalEntry::Airdrop { amount: 50, .. })
+        ));
+        ledger.withdraw(&authority, &keypair, 20).unwrap();
+        ledger.rename_account("alice", "bob").unwrap();
+        ledger.produce_block().unwrap();
+        ledger.verify_blocks().unwrap();
+    }
+
+    #[test]
+    fn ledger_test_account_proof() {
+        let mut ledger = Ledger::new();
+        let mut wallets = Vec::new();
+        for balance in 0..5 {
+            let wallet = Account::new(AccountType::Wallet { balance });
+            handle_add_account(&mut ledger, wallet.clone());
+            wallets.push(wallet);
+        }
+
+        let root = ledger.state_root().unwrap();
+        for wallet in &wallets {
+            let proof = ledger.account_proof(wallet.pubkey).unwrap();
+            assert_eq!(proof.account.lamports, wallet.lamports);
+            assert!(proof.verify(root).unwrap());
+
+            let mut forged = proof.clone();
+            forged.account.lamports += 1;
+            assert!(!forged.verify(root).unwrap());
+        }
+
+        let unknown = Pubkey::new_unique();
+        assert!(matches!(
+            ledger.account_proof(unknown).unwrap_err(),
+            LedgerError::AccountNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn ledger_test_type_index() {
+        let keypair_1 = Keypair::new();
+        let wallet_1 = Account::from_keypair(&keypair_1, AccountType::Wallet { balance: 5 });
+        let wallet_2 = Account::new(AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, wallet_1.clone());
+        handle_add_account(&mut ledger, wallet_2.clone());
+        assert_eq!(ledger.accounts_by_type(AccountKind::Wallet).len(), 2);
+
+        let mut tx = Transaction::new(vec![Instruction::CloseAccount {
+            pubkey: wallet_1.pubkey,
+            destination: wallet_2.pubkey,
+        }]);
+        tx.sign(&keypair_1).unwrap();
+        ledger.process_transaction(&tx).unwrap();
+
+        let wallets = ledger.accounts_by_type(AccountKind::Wallet);
+        assert_eq!(wallets.len(), 1);
+        assert_eq!(wallets.first().unwrap().pubkey, wallet_2.pubkey);
+        assert_eq!(wallets.first().unwrap().lamports, 5);
+        assert!(ledger.accounts_by_type(AccountKind::Stake).is_empty());
+    }
+
+    #[test]
+    fn ledger_test_query() {
+        let validator = Keypair::new().pubkey();
+        let mut ledger = Ledger::new();
+        for balance in [50, 10, 40, 20, 30] {
+            handle_add_account(&mut ledger, Account::new(AccountType::Wallet { balance }));
+        }
+        let stake = Account::new(AccountType::Stake {
+            validator,
+            staked_amount: 25,
+            activation_epoch: None,
+            deactivation_epoch: None,
+        });
+        handle_add_account(&mut ledger, stake.clone());
+        handle_add_account(
+            &mut ledger,
+            Account::new(AccountType::Stake {
+                validator: Keypair::new().pubkey(),
+                staked_amount: 25,
+                activation_epoch: None,
+                deactivation_epoch: None,
+            }),
+        );
+
+        let query = AccountQuery::new()
+            .kind(AccountKind::Wallet)
+            .lamports(15..=45)
+            .order_by(AccountOrder::Lamports)
+            .descending();
+        let balances: Vec<u64> = ledger.query(&query).iter().map(|a| a.lamports).collect();
+        assert_eq!(balances, vec![40, 30, 20]);
+
+        let page = query.offset(1).limit(1);
+        let balances: Vec<u64> = ledger.query(&page).iter().map(|a| a.lamports).collect();
+        assert_eq!(balances, vec![30]);
+
+        let by_validator = ledger.query(&AccountQuery::new().validator(validator));
+        assert_eq!(by_validator.len(), 1);
+        assert_eq!(by_validator.first().unwrap().pubkey, stake.pubkey);
+
+        let by_owner = ledger.query(&AccountQuery::new().owner(SYSTEM_PROGRAM_ID).lamports(..=25));
+        assert_eq!(by_owner.len(), 2);
+        let stakes = ledger.query(&AccountQuery::new().owner(STAKE_PROGRAM_ID));
+        assert_eq!(stakes.len(), 2);
+        assert!(
+            ledger
+                .query(&AccountQuery::new().mint(Pubkey::new_unique()))
+                .is_empty()
+        );
+        assert!(
+            ledger
+                .query(&AccountQuery::new().created_at(..1))
+                .is_empty()
+        );
+        assert_eq!(ledger.query(&AccountQuery::new()).len(), 7);
+    }
+
+    #[test]
+    fn ledger_test_mint_and_burn() {
+        let mint_authority = Keypair::new();
+        let owner = Keypair::new();
+        let delegate = Keypair::new();
+        let mint = Account::new(AccountType::Mint {
+            decimals: 6,
+            supply: 0,
+            mint_authority: Some(mint_authority.pubkey()),
+            freeze_authority: None,
+        });
+        let token_1 = Account::from_keypair(
+            &owner,
+            AccountType::TokenAccount {
+                mint: mint.pubkey,
+                token_balance: 0,
+                delegate: Some(delegate.pubkey()),
+            },
+        );
+        let token_2 = Account::new(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 0,
+            delegate: None,
+        });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, mint.clone());
+        handle_add_account(&mut ledger, token_1.clone());
+        handle_add_account(&mut ledger, token_2.clone());
+
+        let mint_to = Instruction::MintTo {
+            mint: mint.pubkey,
+            to: token_1.pubkey,
+            authority: owner.pubkey(),
+            amount: 100,
+        };
+        let mut tx = Transaction::new(vec![mint_to]);
+        tx.sign(&owner).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidAuthority(_)));
+
+        let mut tx = Transaction::new(vec![
+            Instruction::MintTo {
+                mint: mint.pubkey,
+                to: token_1.pubkey,
+                authority: mint_authority.pubkey(),
+                amount: 100,
+            },
+            Instruction::TokenTransfer {
+                from: token_1.pubkey,
+                to: token_2.pubkey,
+                authority: delegate.pubkey(),
+                amount: 40,
+            },
+            Instruction::Burn {
+                from: token_1.pubkey,
+                mint: mint.pubkey,
+                authority: owner.pubkey(),
+                amount: 25,
+            },
+        ]);
+        tx.sign(&mint_authority)
+            .unwrap()
+            .sign(&delegate)
+            .unwrap()
+            .sign(&owner)
+            .unwrap();
+        ledger.process_transaction(&tx).unwrap();
+
+        let token_balance = |pubkey: &Pubkey| match ledger.get_account(pubkey).unwrap().account_type
+        {
+            AccountType::TokenAccount { token_balance, .. } => token_balance,
+            _ => panic!("account is not a token account"),
+        };
+        assert_eq!(token_balance(&token_1.pubkey), 35);
+        assert_eq!(token_balance(&token_2.pubkey), 40);
+        if let AccountType::Mint { supply, .. } =
+            ledger.get_account(&mint.pubkey).unwrap().account_type
+        {
+            assert_eq!(supply, 75);
+        } else {
+            panic!("account is not a mint");
+        }
+
+        let stranger = Keypair::new();
+        let mut tx = Transaction::new(vec![Instruction::TokenTransfer {
+            from: token_2.pubkey,
+            to: token_1.pubkey,
+            authority: stranger.pubkey(),
+            amount: 1,
+        }]);
+        tx.sign(&stranger).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidAuthority(_)));
+    }
+
+    #[test]
+    fn ledger_test_stake_lifecycle() {
+        let owner = Keypair::new();
+        let stake_keypair = Keypair::new();
+        let stake = stake_keypair.pubkey();
+        let wallet = Account::from_keypair(&owner, AccountType::Wallet { balance: 1_000 });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, wallet.clone());
+
+        let sign_and_process = |ledger: &mut Ledger, instruction: Instruction| {
+            let mut tx = Transaction::new(vec![instruction]);
+            tx.sign(&owner).unwrap().sign(&stake_keypair).unwrap();
+            ledger.process_transaction(&tx)
+        };
+        let withdraw = Instruction::WithdrawStake {
+            stake,
+            to: wallet.pubkey,
+            amount: 400,
+        };
+
+        sign_and_process(
+            &mut ledger,
+            Instruction::DelegateStake {
+                from: wallet.pubkey,
+                stake,
+                validator: Keypair::new().pubkey(),
+                amount: 400,
+            },
+        )
+        .unwrap();
+        assert_eq!(ledger.get_account(&wallet.pubkey).unwrap().lamports, 600);
+        assert_eq!(ledger.total_supply().unwrap(), 1_000);
+
+        let activation = ledger.stake_activation(stake).unwrap();
+        assert_eq!(activation.status, StakeStatus::Activating);
+        assert_eq!(activation.effective, 0);
+        ledger.advance_epoch().unwrap();
+        assert_eq!(ledger.stake_activation(stake).unwrap().effective, 100);
+        let err = sign_and_process(&mut ledger, withdraw.clone()).unwrap_err();
+        assert!(matches!(err, LedgerError::StakeStillActive { .. }));
+
+        for _ in 0..3 {
+            ledger.advance_epoch().unwrap();
+        }
+        let activation = ledger.stake_activation(stake).unwrap();
+        assert_eq!(activation.status, StakeStatus::Active);
+        assert_eq!(activation.effective, 400);
+
+        let deactivate = Instruction::DeactivateStake { stake };
+        sign_and_process(&mut ledger, deactivate.clone()).unwrap();
+        let err = sign_and_process(&mut ledger, deactivate).unwrap_err();
+        assert!(matches!(err, LedgerError::StakeAlreadyDeactivated(_)));
+
+        ledger.advance_epoch().unwrap();
+        let activation = ledger.stake_activation(stake).unwrap();
+        assert_eq!(activation.status, StakeStatus::Deactivating);
+        assert_eq!(activation.effective, 300);
+        let err = sign_and_process(&mut ledger, withdraw.clone()).unwrap_err();
+        assert!(matches!(err, LedgerError::StakeStillActive { .. }));
+
+        for _ in 0..3 {
+            ledger.advance_epoch().unwrap();
+        }
+        assert_eq!(
+            ledger.stake_activation(stake).unwrap().status,
+            StakeStatus::Deactivated
+        );
+        sign_and_process(&mut ledger, withdraw).unwrap();
+        assert_eq!(ledger.get_account(&wallet.pubkey).unwrap().lamports, 1_000);
+        assert_eq!(ledger.get_account(&stake).unwrap().lamports, 0);
+    }
+
+    #[test]
+    fn ledger_test_arithmetic_overflow() {
+        let keypair = Keypair::new();
+        let wallet = Account::from_keypair(&keypair, AccountType::Wallet { balance: 10 });
+        let rich_wallet = Account::new(AccountType::Wallet { balance: u64::MAX });
+
+        let mut ledger = Ledger::new();
+        handle_add_account(&mut ledger, wallet.clone());
+        handle_add_account(&mut ledger, rich_wallet.clone());
+
+        let err = ledger
+            .transfer(&keypair, rich_wallet.pubkey, 1)
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::ArithmeticOverflow));
+        assert_eq!(ledger.get_account(&wallet.pubkey).unwrap().lamports, 10);
+        assert_eq!(
+            ledger.get_account(&rich_wallet.pubkey).unwrap().lamports,
+            u64::MAX
+        );
+
+        assert!(matches!(
+            ledger.total_supply().unwrap_err(),
+            LedgerError::ArithmeticOverflow
+        ));
+        let report = ledger.supply_report();
+        assert_eq!(report.total, u64::MAX as u128 + 10);
+        assert_eq!(report.by_kind[&AccountKind::Wallet], report.total);
+
+        let authority = Keypair::new();
+        let mint = Account::new(AccountType::Mint {
+            decimals: 0,
+            supply: u64::MAX,
+            mint_authority: Some(authority.pubkey()),
+            freeze_authority: None,
+        });
+        let token = Account::new(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 0,
+            delegate: None,
+        });
+        handle_add_account(&mut ledger, mint.clone());
+        handle_add_account(&mut ledger, token.clone());
+        let mut tx = Transaction::new(vec![Instruction::MintTo {
+            mint: mint.pubkey,
+            to: token.pubkey,
+            authority: authority.pubkey(),
+            amount: 1,
+        }]);
+        tx.sign(&authority).unwrap();
+        let err = ledger.process_transaction(&tx).unwrap_err();
+        assert!(matches!(err, LedgerError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn ledger_test_verify() {
+        let payer = Keypair::new();
+        let wallet = Account::from_keypair(&payer, AccountType::Wallet { balance: 1_000 });
+        let other = Account::new(AccountType::Wallet { balance: 0 });
+        let authority = Keypair::new();
+        let mint = Account::new(AccountType::Mint {
+            decimals: 0,
+            supply: 0,
+            mint_authority: Some(authority.pubkey()),
+            freeze_authority: None,
+        });
+        let token = Account::new(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 0,
+            delegate: None,
+        });
+
+        let mut ledger = Ledger::new();
+        for account in [&wallet, &other, &mint, &token] {
+            handle_add_account(&mut ledger, account.clone());
+        }
+        ledger.set_fees(FeeSchedule::new(5, 0)).unwrap();
+        let new_wallet = Keypair::new();
+        let mut tx = Transaction::new(vec![
+            Instruction::Transfer {
+                from: payer.pubkey(),
+                to: other.pubkey,
+                amount: 100,
+            },
+            Instruction::CreateAccount {
+                account: Account::from_keypair(&new_wallet, AccountType::Wallet { balance: 50 }),
+            },
+            Instruction::MintTo {
+                mint: mint.pubkey,
+                to: token.pubkey,
+                authority: authority.pubkey(),
+                amount: 7,
+            },
+        ]);
+        tx.sign(&payer)
+            .unwrap()
+            .sign(&new_wallet)
+            .unwrap()
+            .sign(&authority)
+            .unwrap();
+        ledger.process_transaction(&tx).unwrap();
+        assert!(ledger.verify().is_ok());
+        // the fee of the three signatures is burned, the fee payer funds the
+        // created wallet
+        let supply = (wallet.lamports + mint.lamports - 15) as u128;
+        assert_eq!(ledger.supply_report().total, supply);
+        assert_eq!(
+            ledger.get_account(&payer.pubkey()).unwrap().lamports,
+            1_000 - 100 - 15 - 50
+        );
+
+        let drifted = ledger.accounts.get_mut(&other.pubkey).unwrap();
+        drifted.lamports += 1;
+        if let AccountType::TokenAccount {
+            ref mut token_balance,
+            ..
+        } = ledger.accounts.get_mut(&token.pubkey).unwrap().account_type
+        {
+            *token_balance += 1;
+        }
+        let report = ledger.verify();
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation::WalletBalanceMismatch {
+                    pubkey: other.pubkey,
+                    balance: 100,
+                    lamports: 101,
+                },
+                Violation::SupplyNotConserved {
+                    expected: supply,
+                    actual: supply + 1,
+                },
+                Violation::TokenSupplyMismatch {
+                    mint: mint.pubkey,
+                    supply: 7,
+                    held: 8,
+                },
+            ]
+        );
+        assert!(matches!(
+            report.into_result().unwrap_err(),
+            LedgerError::InvariantViolation(_)
+        ));
+    }
+
+    #[test]
+    fn ledger_test_names_and_airdrops() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+        let snapshot_path = dir.path("ledger.bin");
+
+        let wallet = Account::new(AccountType::Wallet { balance: 10 });
+        let program = Account::new(AccountType::Program {
+            executable: false,
+            program_data: vec![],
+        });
+
+        let faucet = Keypair::new();
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        ledger
+            .set_faucet_policy(&faucet, FaucetPolicy::new(faucet.pubkey()))
+            .unwrap();
+        ledger.add_named_account("Alice", wallet.clone()).unwrap();
+        handle_add_account(&mut ledger, program.clone());
+
+        assert_eq!(
+            ledger.account_by_name("ALICE").unwrap().pubkey,
+            wallet.pubkey
+        );
+        assert!(ledger.account_by_name("bob").is_none());
+        let err = ledger
+            .add_named_account("alice", Account::new(AccountType::Wallet { balance: 0 }))
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::NameTaken { .. }));
+        assert_eq!(ledger.accounts.len(), 2);
+        let err = ledger
+            .register_name(&program.pubkey.to_string(), &program.pubkey)
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidName(_)));
+        ledger.register_name("loader", &program.pubkey).unwrap();
+
+        assert_eq!(ledger.airdrop(&faucet, "alice", 25).unwrap(), 35);
+        let err = ledger.airdrop(&faucet, "loader", 25).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+        let err = ledger.airdrop(&faucet, "alice", u64::MAX).unwrap_err();
+        assert!(matches!(err, LedgerError::ArithmeticOverflow));
+        assert!(ledger.verify().is_ok());
+        ledger.save_ledger(&snapshot_path).unwrap();
+        ledger.airdrop(&faucet, wallet.pubkey, 5).unwrap();
+        ledger.rename_account("loader", "Runtime").unwrap();
+
+        let replayed = Ledger::replay(&journal_path).unwrap();
+        let from_snapshot = Ledger::replay_from_snapshot(&snapshot_path, &journal_path).unwrap();
+        for restored in [&replayed, &from_snapshot] {
+            let alice = restored.account_by_name("alice").unwrap();
+            assert_eq!(alice.lamports, 40);
+            assert!(matches!(
+                alice.account_type,
+                AccountType::Wallet { balance: 40 }
+            ));
+            assert_eq!(
+                restored.account_by_name("runtime").unwrap().pubkey,
+                program.pubkey
+            );
+            assert!(restored.account_by_name("loader").is_none());
+            assert!(restored.verify().is_ok());
+        }
+    }
+
+    #[test]
+    fn ledger_test_name_lookups() {
+        let keypair = Keypair::new();
+        let treasury = Account::from_keypair(&keypair, AccountType::Wallet { balance: 100 });
+        let payroll = Account::new(AccountType::Wallet { balance: 0 });
+        let mint = Account::new(AccountType::Mint {
+            decimals: 0,
+            supply: 0,
+            mint_authority: None,
+            freeze_authority: None,
+        });
+        let token = Account::new(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 0,
+            delegate: None,
+        });
+
+        let mut ledger = Ledger::new();
+        ledger
+            .add_named_account("Treasury", treasury.clone())
+            .unwrap();
+        ledger
+            .add_named_account("payroll", payroll.clone())
+            .unwrap();
+        ledger.add_named_account("usdc", mint.clone()).unwrap();
+        handle_add_account(&mut ledger, token.clone());
+        ledger.register_name("salaries", &payroll.pubkey).unwrap();
+
+        ledger.transfer(&keypair, "PAYROLL", 30).unwrap();
+        ledger.transfer(&keypair, payroll.pubkey, 10).unwrap();
+        assert_eq!(ledger.find_account("salaries").unwrap().lamports, 40);
+        assert_eq!(
+            ledger
+                .find_account(&treasury.pubkey.to_string())
+                .unwrap()
+                .lamports,
+            60
+        );
+        let err = ledger.transfer(&keypair, "marketing", 1).unwrap_err();
+        assert!(matches!(err, LedgerError::AccountNotFound(_)));
+
+        assert_eq!(
+            ledger.names_of(&payroll.pubkey).collect::<Vec<_>>(),
+            ["payroll", "salaries"]
+        );
+        let err = ledger.rename_account("salaries", "Treasury").unwrap_err();
+        assert!(matches!(err, LedgerError::NameTaken { .. }));
+        assert_eq!(
+            ledger.rename_account("salaries", "wages").unwrap(),
+            payroll.pubkey
+        );
+        assert_eq!(
+            ledger.names_of(&payroll.pubkey).collect::<Vec<_>>(),
+            ["payroll", "wages"]
+        );
+
+        let tokens = ledger.query(&AccountQuery::new().mint("USDC"));
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].pubkey, token.pubkey);
+        assert!(
+            ledger
+                .query(&AccountQuery::new().mint("unknown"))
+                .is_empty()
+        );
+        assert_eq!(
+            ledger.account_proof("treasury").unwrap().account.pubkey,
+            treasury.pubkey
+        );
+        let err = ledger.stake_activation("treasury").unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+    }
+
+    #[test]
+    fn ledger_test_faucet() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+        let snapshot_path = dir.path("ledger.bin");
+
+        let authority = Keypair::new();
+        let owner = Keypair::new();
+        let wallet = Account::from_keypair(&owner, AccountType::Wallet { balance: 0 });
+
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        let policy = FaucetPolicy::new(authority.pubkey())
+            .with_airdrop_limit(100)
+            .with_withdraw_limit(30);
+        ledger.set_faucet_policy(&owner, policy.clone()).unwrap();
+        // only the authority it set can change it now
+        let err = ledger
+            .set_faucet_policy(&owner, FaucetPolicy::new(owner.pubkey()))
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidAuthority(_)));
+        ledger
+            .set_faucet_policy(&authority, policy.clone())
+            .unwrap();
+        ledger.add_named_account("tester", wallet.clone()).unwrap();
+
+        // a ledger created without a faucet never mints
+        let mut unfunded = Ledger::new();
+        unfunded
+            .add_named_account("tester", wallet.clone())
+            .unwrap();
+        let err = unfunded.airdrop(&authority, "tester", 10).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidAuthority(_)));
+        let err = unfunded.set_faucet_policy(&authority, policy).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidAuthority(_)));
+        assert_eq!(ledger.airdrop(&authority, "tester", 80).unwrap(), 80);
+        let err = ledger.airdrop(&owner, "tester", 10).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidAuthority(_)));
+        let err = ledger.airdrop(&authority, "tester", 30).unwrap_err();
+        assert!(matches!(
+            err,
+            LedgerError::RateLimitExceeded { remaining: 20, .. }
+        ));
+        assert_eq!(ledger.withdraw(&authority, &owner, 25).unwrap(), 55);
+        let err = ledger.withdraw(&authority, &owner, 10).unwrap_err();
+        assert!(matches!(
+            err,
+            LedgerError::RateLimitExceeded { remaining: 5, .. }
+        ));
+        assert_eq!(ledger.total_supply().unwrap(), 55);
+        assert!(ledger.verify().is_ok());
+
+        // the usage of the epoch is saved with the ledger
+        ledger.save_ledger(&snapshot_path).unwrap();
+        let mut loaded = Ledger::load_ledger(&snapshot_path).unwrap();
+        let err = loaded.airdrop(&authority, "tester", 30).unwrap_err();
+        assert!(matches!(err, LedgerError::RateLimitExceeded { .. }));
+        loaded.advance_epoch().unwrap();
+        assert_eq!(loaded.airdrop(&authority, "tester", 100).unwrap(), 155);
+
+        ledger.advance_epoch().unwrap();
+        ledger.airdrop(&authority, wallet.pubkey, 100).unwrap();
+        let err = ledger.withdraw(&authority, &owner, 500).unwrap_err();
+        assert!(matches!(err, LedgerError::RateLimitExceeded { .. }));
+        let replayed = Ledger::replay(&journal_path).unwrap();
+        let from_snapshot = Ledger::replay_from_snapshot(&snapshot_path, &journal_path).unwrap();
+        for restored in [&replayed, &from_snapshot] {
+            assert_eq!(restored.total_supply().unwrap(), 155);
+            assert_eq!(restored.faucet(), ledger.faucet());
+            assert!(restored.verify().is_ok());
+        }
+    }
+
+    #[test]
+    fn ledger_test_close_account() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+        let snapshot_path = dir.path("ledger.bin");
+
+        let keyed = |account_type| {
+            let keypair = Keypair::new();
+            (Account::from_keypair(&keypair, account_type), keypair)
+        };
+        let destination = Account::new(AccountType::Wallet { balance: 0 });
+        let (throwaway, throwaway_key) = keyed(AccountType::Wallet { balance: 40 });
+        let (mint, mint_key) = keyed(AccountType::Mint {
+            decimals: 0,
+            supply: 5,
+            mint_authority: None,
+            freeze_authority: None,
+        });
+        let (full_token, full_token_key) = keyed(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 5,
+            delegate: None,
+        });
+        let (empty_token, empty_token_key) = keyed(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 0,
+            delegate: None,
+        });
+        let stake = |activation_epoch| {
+            keyed(AccountType::Stake {
+                validator: Pubkey::new_unique(),
+                staked_amount: 10,
+                activation_epoch,
+                deactivation_epoch: None,
+            })
+        };
+        let (active_stake, active_stake_key) = stake(Some(0));
+        let (inactive_stake, inactive_stake_key) = stake(None);
+
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        ledger
+            .add_named_account("treasury", destination.clone())
+            .unwrap();
+        ledger
+            .add_named_account("scratch", throwaway.clone())
+            .unwrap();
+        for account in [
+            &mint,
+            &full_token,
+            &empty_token,
+            &active_stake,
+            &inactive_stake,
+        ] {
+            handle_add_account(&mut ledger, account.clone());
+        }
+        let supply = ledger.total_supply().unwrap();
+
+        // the account to close must sign
+        let mut unsigned = Transaction::new(vec![Instruction::CloseAccount {
+            pubkey: throwaway.pubkey,
+            destination: destination.pubkey,
+        }]);
+        unsigned.sign(&Keypair::new()).unwrap();
+        let err = ledger.process_transaction(&unsigned).unwrap_err();
+        assert!(matches!(err, LedgerError::MissingSignature(_)));
+
+        let closure = ledger.close_account(&throwaway_key, "treasury").unwrap();
+        assert_eq!(closure.pubkey, throwaway.pubkey);
+        assert_eq!(closure.lamports, 40);
+        assert!(ledger.get_account(&throwaway.pubkey).is_none());
+        assert!(ledger.find_account("scratch").is_none());
+        assert_eq!(
+            ledger.get_account(&destination.pubkey).unwrap().lamports,
+            40
+        );
+
+        for keypair in [&full_token_key, &mint_key] {
+            let err = ledger.close_account(keypair, "treasury").unwrap_err();
+            assert!(matches!(err, LedgerError::AccountNotEmpty { .. }));
+        }
+        let err = ledger
+            .close_account(&active_stake_key, "treasury")
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::StakeStillActive { .. }));
+        let err = ledger
+            .close_account(&empty_token_key, mint.pubkey)
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+        ledger.close_account(&empty_token_key, "treasury").unwrap();
+        ledger.save_ledger(&snapshot_path).unwrap();
+        ledger
+            .close_account(&inactive_stake_key, "treasury")
+            .unwrap();
+
+        assert_eq!(
+            ledger
+                .closures()
+                .iter()
+                .map(|closure| closure.pubkey)
+                .collect::<Vec<_>>(),
+            [throwaway.pubkey, empty_token.pubkey, inactive_stake.pubkey]
+        );
+        assert_eq!(ledger.accounts.len(), 4);
+        assert_eq!(ledger.total_supply().unwrap(), supply);
+        assert!(ledger.verify().is_ok());
+        // the closures are the transactions of the next block
+        let block = ledger.produce_block().unwrap();
+        assert_eq!(
+            block
+                .entries
+                .iter()
+                .filter(|entry| matches!(entry, JournalEntry::Transaction(_)))
+                .count(),
+            3
+        );
+
+        let loaded = Ledger::load_ledger(&snapshot_path).unwrap();
+        assert_eq!(loaded.closures(), &ledger.closures()[..2]);
+        let replayed = Ledger::replay(&journal_path).unwrap();
+        let from_snapshot = Ledger::replay_from_snapshot(&snapshot_path, &journal_path).unwrap();
+        for restored in [&replayed, &from_snapshot] {
+            assert_eq!(restored.closures(), ledger.closures());
+            assert_eq!(restored.state_root().unwrap(), ledger.state_root().unwrap());
+        }
+    }
+
+    #[test]
+    fn ledger_test_invoke_program() {
+        let dir = TestDir::new();
+        let journal_path = dir.path("ledger.journal");
+
+        // moves input[0] lamports from account 1 to account 0 and counts the
+        // deposits in the data of account 0
+        let push = |value: u64| [&[op::PUSH][..], &value.to_le_bytes()].concat();
+        let code = [
+            push(1),
+            push(0),
+            push(0),
+            vec![op::INPUT, op::TRANSFER],
+            push(0),
+            push(0),
+            push(0),
+            push(0),
+            vec![op::LOAD],
+            push(1),
+            vec![op::ADD, op::STORE],
+        ]
+        .concat();
+
+        let owner = Keypair::new();
+        let wallet = Account::from_keypair(&owner, AccountType::Wallet { balance: 1_000 });
+        let program = Account::new(AccountType::Program {
+            executable: true,
+            program_data: code,
+        });
+        let state = |owner| {
+            Account::new(AccountType::Program {
+                executable: false,
+                program_data: vec![0; 8],
+            })
+            .owned_by(owner)
+        };
+        let vault = state(program.pubkey);
+        let foreign = state(Pubkey::new_unique());
+
+        let mut ledger = Ledger::new();
+        ledger.open_journal(&journal_path).unwrap();
+        for account in [&wallet, &program, &vault, &foreign] {
+            handle_add_account(&mut ledger, account.clone());
+        }
+        let supply = ledger.total_supply().unwrap();
+        let deposit = |amount: u64| amount.to_le_bytes().to_vec();
+
+        for _ in 0..2 {
+            ledger
+                .invoke(
+                    &owner,
+                    program.pubkey,
+                    [vault.pubkey, wallet.pubkey],
+                    deposit(100),
+                )
+                .unwrap();
+        }
+        let stored = ledger.get_account(&vault.pubkey).unwrap();
+        assert_eq!(stored.lamports, vault.lamports + 200);
+        assert!(matches!(
+            &stored.account_type,
+            AccountType::Program { program_data, .. } if program_data[..] == 2u64.to_le_bytes()
+        ));
+        assert_eq!(ledger.get_account(&wallet.pubkey).unwrap().lamports, 800);
+
+        // the transfer to the foreign account succeeds but storing fails,
+        // the whole transaction is rolled back
+        let err = ledger
+            .invoke(
+                &owner,
+                program.pubkey,
+                [foreign.pubkey, wallet.pubkey],
+                deposit(100),
+            )
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::ProgramFailed { .. }));
+        let err = ledger
+            .invoke(
+                &owner,
+                program.pubkey,
+                [vault.pubkey, wallet.pubkey],
+                deposit(5_000),
+            )
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InsufficientFunds { .. }));
+        let err = ledger
+            .invoke(&owner, vault.pubkey, [wallet.pubkey], deposit(1))
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+
+        // without the wallet signature the program can not debit it
+        let unsigned = Transaction::new(vec![Instruction::Invoke {
+            program: program.pubkey,
+            accounts: vec![vault.pubkey, wallet.pubkey],
+            signers: vec![],
+            data: deposit(100),
+        }]);
+        let err = ledger.process_transaction(&unsigned).unwrap_err();
+        assert!(matches!(err, LedgerError::ProgramFailed { .. }));
+
+        assert_eq!(ledger.get_account(&wallet.pubkey).unwrap().lamports, 800);
+        assert_eq!(ledger.total_supply().unwrap(), supply);
+        assert!(ledger.verify().is_ok());
+
+        let replayed = Ledger::replay(&journal_path).unwrap();
+        assert_eq!(replayed.state_root().unwrap(), ledger.state_root().unwrap());
+    }
+
+    #[test]
+    fn ledger_test_invoke_keeps_debited_wallets_rent_exempt() {
+        // moves input[0] lamports from account 1 to account 0
+        let push = |value: u64| [&[op::PUSH][..], &value.to_le_bytes()].concat();
+        let code = [push(1), push(0), push(0), vec![op::INPUT, op::TRANSFER]].concat();
+
+        let rent = Rent::default();
+        let owner = Keypair::new();
+        let minimum =
+            rent.minimum_balance(Account::new(AccountType::Wallet { balance: 0 }).data_len());
+        let wallet = Account::from_keypair(
+            &owner,
+            AccountType::Wallet {
+                balance: minimum + 100,
+            },
+        );
+        let program = Account::new(AccountType::Program {
+            executable: true,
+            program_data: code,
+        });
+        let vault = Account::new(AccountType::Program {
+            executable: false,
+            program_data: vec![0; 8],
+        })
+        .owned_by(program.pubkey);
+
+        let mut ledger = Ledger::new();
+        ledger.set_rent(rent).unwrap();
+        for account in [&wallet, &program, &vault] {
+            handle_add_account(&mut ledger, account.clone());
+        }
+        let mut drain = |amount: u64| {
+            ledger.invoke(
+                &owner,
+                program.pubkey,
+                [vault.pubkey, wallet.pubkey],
+                amount.to_le_bytes().to_vec(),
+            )
+        };
+
+        // the signer wallet is not owned by the program, draining it below
+        // the exemption minimum still fails
+        let err = drain(101).unwrap_err();
+        assert!(matches!(err, LedgerError::RentNotExempt { .. }));
+        drain(100).unwrap();
+        assert_eq!(
+            ledger.get_account(&wallet.pubkey).unwrap().lamports,
+            minimum
+        );
+        assert_eq!(
+            ledger.get_account(&vault.pubkey).unwrap().lamports,
+            vault.lamports + 100
+        );
+    }
+
+    /// Scratch directory of a test, removed with its files when dropped.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("ledger-test-{}", Keypair::new().pubkey())))
+        }
+
+        fn path(&self, file: &str) -> String {
+            self.0.join(file).to_string_lossy().to_string()
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
     fn handle_add_account(l: &mut Ledger, acc: Account) {
         if let Err(err) = l.add_account(acc) {
             panic!("{}", err.to_string());
@@ -0,0 +1,135 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::hash::{Hash, hashv};
+
+use crate::pkg::{account::Account, errors::LedgerError};
+
+/// prefixes keeping a leaf from ever hashing like an inner node
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn hash_leaf(data: &[u8]) -> Hash {
+    hashv(&[LEAF_PREFIX, data])
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    hashv(&[NODE_PREFIX, left.as_ref(), right.as_ref()])
+}
+
+/// Root of the binary Merkle tree over `leaves`, an odd node is promoted to
+/// the next level as is. The root of no leaves is the default hash.
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return Hash::default();
+    }
+
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+
+    level[0]
+}
+
+/// Proof that the leaf at `index` is part of the tree over `leaves`, `None`
+/// when there is no such leaf.
+pub fn merkle_proof(leaves: &[Hash], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut steps = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut position = index;
+    while level.len() > 1 {
+        let sibling = position ^ 1;
+        // a promoted node has no sibling on this level
+        if let Some(hash) = level.get(sibling) {
+            steps.push(ProofStep {
+                sibling: *hash,
+                sibling_is_left: sibling < position,
+            });
+        }
+        level = next_level(&level);
+        position /= 2;
+    }
+
+    Some(MerkleProof { steps })
+}
+
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ProofStep {
+    pub sibling: Hash,
+    pub sibling_is_left: bool,
+}
+
+/// Sibling hashes from a leaf up to the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct MerkleProof {
+    pub steps: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+    /// Root of the tree `leaf` belongs to according to this proof.
+    pub fn root(&self, leaf: Hash) -> Hash {
+        self.steps.iter().fold(leaf, |hash, step| {
+            if step.sibling_is_left {
+                hash_node(&step.sibling, &hash)
+            } else {
+                hash_node(&hash, &step.sibling)
+            }
+        })
+    }
+
+    pub fn verify(&self, leaf: Hash, root: Hash) -> bool {
+        self.root(leaf) == root
+    }
+}
+
+/// An account with the proof it is part of a ledger state root, enough to
+/// check its balance without the rest of the ledger.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct AccountProof {
+    pub account: Account,
+    pub proof: MerkleProof,
+}
+
+impl AccountProof {
+    pub fn verify(&self, state_root: Hash) -> Result<bool, LedgerError> {
+        let leaf = hash_leaf(&self.account.save_to_bytes()?);
+        Ok(self.proof.verify(leaf, state_root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::hash;
+
+    #[test]
+    fn test_merkle_proofs() {
+        assert_eq!(merkle_root(&[]), Hash::default());
+        assert!(merkle_proof(&[], 0).is_none());
+
+        for size in 1..=9u8 {
+            let leaves: Vec<Hash> = (0..size).map(|i| hash_leaf(&[i])).collect();
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, index).unwrap();
+                assert!(proof.verify(*leaf, root));
+                assert!(!proof.verify(hash(b"not a leaf"), root));
+            }
+            assert!(merkle_proof(&leaves, size as usize).is_none());
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use crate::pkg::errors::LedgerError;
+
+/// An account given either by its pubkey or by one of its names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountRef {
+    Pubkey(Pubkey),
+    Name(String),
+}
+
+impl From<Pubkey> for AccountRef {
+    fn from(pubkey: Pubkey) -> Self {
+        Self::Pubkey(pubkey)
+    }
+}
+
+impl From<&Pubkey> for AccountRef {
+    fn from(pubkey: &Pubkey) -> Self {
+        Self::Pubkey(*pubkey)
+    }
+}
+
+/// A valid base58 pubkey is a key, anything else is a name.
+impl From<&str> for AccountRef {
+    fn from(value: &str) -> Self {
+        match Pubkey::from_str(value.trim()) {
+            Ok(pubkey) => Self::Pubkey(pubkey),
+            Err(_) => Self::Name(value.to_string()),
+        }
+    }
+}
+
+impl From<&String> for AccountRef {
+    fn from(value: &String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl Display for AccountRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pubkey(pubkey) => write!(f, "{}", pubkey),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Unique human-readable names of accounts, looked up regardless of case.
+/// An account can have any number of names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameRegistry {
+    /// lowercased name to the key it names
+    names: BTreeMap<String, Pubkey>,
+    /// reverse index of the names of each key, rebuilt on load
+    by_key: BTreeMap<Pubkey, BTreeSet<String>>,
+}
+
+impl NameRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names `pubkey`, failing with `NameTaken` when the name already names
+    /// an account.
+    pub fn register(&mut self, name: &str, pubkey: Pubkey) -> Result<(), LedgerError> {
+        let name = self.check_available(name)?;
+        self.insert(name, pubkey);
+
+        Ok(())
+    }
+
+    /// Normalized `name` when it is valid and not taken yet.
+    pub fn check_available(&self, name: &str) -> Result<String, LedgerError> {
+        let name = normalize(name)?;
+        if let Some(pubkey) = self.names.get(&name) {
+            return Err(LedgerError::NameTaken {
+                name,
+                pubkey: pubkey.to_string(),
+            });
+        }
+
+        Ok(name)
+    }
+
+    /// Moves the account named `from` to the name `to` and returns its key.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<Pubkey, LedgerError> {
+        let pubkey = self
+            .resolve(from)
+            .ok_or_else(|| LedgerError::AccountNotFound(from.to_string()))?;
+        let to = self.check_available(to)?;
+
+        self.remove(from);
+        self.insert(to, pubkey);
+        Ok(pubkey)
+    }
+
+    /// Forgets `name`, returning the key it named.
+    pub fn remove(&mut self, name: &str) -> Option<Pubkey> {
+        let name = name.trim().to_lowercase();
+        let pubkey = self.names.remove(&name)?;
+        if let Some(names) = self.by_key.get_mut(&pubkey) {
+            names.remove(&name);
+            if names.is_empty() {
+                self.by_key.remove(&pubkey);
+            }
+        }
+
+        Some(pubkey)
+    }
+
+    /// Forgets every name of `pubkey`.
+    pub fn remove_key(&mut self, pubkey: &Pubkey) {
+        for name in self.by_key.remove(pubkey).unwrap_or_default() {
+            self.names.remove(&name);
+        }
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<Pubkey> {
+        self.names.get(&name.trim().to_lowercase()).copied()
+    }
+
+    /// Key of `account`, pubkeys resolve to themselves.
+    pub fn resolve_ref(&self, account: &AccountRef) -> Option<Pubkey> {
+        match account {
+            AccountRef::Pubkey(pubkey) => Some(*pubkey),
+            AccountRef::Name(name) => self.resolve(name),
+        }
+    }
+
+    /// Names of `pubkey` in alphabetical order.
+    pub fn names_of(&self, pubkey: &Pubkey) -> impl Iterator<Item = &str> {
+        self.by_key
+            .get(pubkey)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Pubkey)> {
+        self.names
+            .iter()
+            .map(|(name, pubkey)| (name.as_str(), pubkey))
+    }
+
+    fn insert(&mut self, name: String, pubkey: Pubkey) {
+        self.by_key.entry(pubkey).or_default().insert(name.clone());
+        self.names.insert(name, pubkey);
+    }
+}
+
+/// Only the names are stored, the reverse index is derived from them.
+impl BorshSerialize for NameRegistry {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.names.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for NameRegistry {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut registry = Self::new();
+        for (name, pubkey) in BTreeMap::<String, Pubkey>::deserialize_reader(reader)? {
+            registry.insert(name, pubkey);
+        }
+
+        Ok(registry)
+    }
+}
+
+/// Lowercased and trimmed name. A name can not be empty nor a valid pubkey,
+/// it would be ambiguous with the key it looks like.
+pub fn normalize(name: &str) -> Result<String, LedgerError> {
+    let name = name.trim();
+    if name.is_empty() || Pubkey::from_str(name).is_ok() {
+        return Err(LedgerError::InvalidName(name.to_string()));
+    }
+
+    Ok(name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::to_vec;
+
+    #[test]
+    fn test_name_registry() {
+        let treasury = Pubkey::new_unique();
+        let mut names = NameRegistry::new();
+        names.register("Treasury", treasury).unwrap();
+
+        assert_eq!(names.resolve("treasury"), Some(treasury));
+        assert_eq!(names.resolve(" TREASURY "), Some(treasury));
+        assert_eq!(names.resolve("payroll"), None);
+        assert!(matches!(
+            names.register("treasury", Pubkey::new_unique()),
+            Err(LedgerError::NameTaken { .. })
+        ));
+        assert!(matches!(
+            names.register("  ", treasury),
+            Err(LedgerError::InvalidName(_))
+        ));
+        assert!(matches!(
+            names.register(&treasury.to_string(), treasury),
+            Err(LedgerError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn test_name_registry_rename_and_reverse_lookup() {
+        let treasury = Pubkey::new_unique();
+        let payroll = Pubkey::new_unique();
+        let mut names = NameRegistry::new();
+        names.register("treasury", treasury).unwrap();
+        names.register("vault", treasury).unwrap();
+        names.register("payroll", payroll).unwrap();
+        assert_eq!(
+            names.names_of(&treasury).collect::<Vec<_>>(),
+            ["treasury", "vault"]
+        );
+
+        assert_eq!(names.rename("Vault", "Reserve").unwrap(), treasury);
+        assert_eq!(names.resolve("vault"), None);
+        assert_eq!(names.resolve("reserve"), Some(treasury));
+        assert!(matches!(
+            names.rename("reserve", "payroll"),
+            Err(LedgerError::NameTaken { .. })
+        ));
+        assert!(matches!(
+            names.rename("vault", "anything"),
+            Err(LedgerError::AccountNotFound(_))
+        ));
+
+        let decoded = NameRegistry::try_from_slice(&to_vec(&names).unwrap()).unwrap();
+        assert_eq!(decoded, names);
+        assert_eq!(
+            decoded.names_of(&treasury).collect::<Vec<_>>(),
+            ["reserve", "treasury"]
+        );
+
+        names.remove_key(&treasury);
+        assert_eq!(names.names_of(&treasury).count(), 0);
+        assert_eq!(names.resolve("treasury"), None);
+        assert_eq!(names.resolve("payroll"), Some(payroll));
+
+        assert_eq!(
+            AccountRef::from(payroll.to_string().as_str()),
+            AccountRef::Pubkey(payroll)
+        );
+        assert_eq!(
+            names.resolve_ref(&AccountRef::from("PAYROLL")),
+            Some(payroll)
+        );
+    }
+}
@@ -0,0 +1,897 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+use crate::pkg::{
+    account::{Account, AccountKind, AccountType, BPF_LOADER_ID},
+    errors::LedgerError,
+    rent::Rent,
+    stake::StakeActivation,
+    transaction::Instruction,
+    vm::{self, COMPUTE_BUDGET, ProgramAccount},
+};
+
+/// An account closed by a `CloseAccount` instruction.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AccountClosure {
+    pub pubkey: Pubkey,
+    /// wallet the remaining lamports were drained to
+    pub destination: Pubkey,
+    pub lamports: u64,
+    pub epoch: u64,
+}
+
+/// Working copy of the accounts a transaction touches. Instructions only ever
+/// mutate this copy, the ledger commits it once every instruction succeeded.
+/// A `None` entry is an account that does not exist (yet, or anymore).
+#[derive(Debug)]
+pub struct TransactionContext {
+    accounts: BTreeMap<Pubkey, Option<Account>>,
+    epoch: u64,
+    rent: Rent,
+    /// fee payer of the transaction, it funds the accounts it creates
+    payer: Option<Pubkey>,
+    /// fees taken out of the supply
+    burned: u64,
+    closures: Vec<AccountClosure>,
+}
+
+impl TransactionContext {
+    pub fn new(epoch: u64, rent: Rent) -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            epoch,
+            rent,
+            payer: None,
+            burned: 0,
+            closures: Vec::new(),
+        }
+    }
+
+    pub fn with_payer(mut self, payer: Option<Pubkey>) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    pub fn burned(&self) -> u64 {
+        self.burned
+    }
+
+    pub fn closures(&self) -> &[AccountClosure] {
+        &self.closures
+    }
+
+    pub fn load(&mut self, pubkey: &Pubkey, account: Option<Account>) {
+        self.accounts.insert(*pubkey, account);
+    }
+
+    pub fn into_accounts(self) -> BTreeMap<Pubkey, Option<Account>> {
+        self.accounts
+    }
+
+    fn get(&self, pubkey: &Pubkey) -> Result<&Account, LedgerError> {
+        self.accounts
+            .get(pubkey)
+            .and_then(|acc| acc.as_ref())
+            .ok_or_else(|| LedgerError::AccountNotFound(pubkey.to_string()))
+    }
+
+    fn get_mut(&mut self, pubkey: &Pubkey) -> Result<&mut Account, LedgerError> {
+        self.accounts
+            .get_mut(pubkey)
+            .and_then(|acc| acc.as_mut())
+            .ok_or_else(|| LedgerError::AccountNotFound(pubkey.to_string()))
+    }
+}
+
+pub fn process_instruction(
+    ctx: &mut TransactionContext,
+    instruction: &Instruction,
+) -> Result<(), LedgerError> {
+    match instruction {
+        Instruction::Transfer { from, to, amount } => transfer(ctx, from, to, *amount),
+        Instruction::CreateAccount { account } => fund_account(ctx, account),
+        Instruction::CloseAccount {
+            pubkey,
+            destination,
+        } => close_account(ctx, pubkey, destination),
+        Instruction::TokenTransfer {
+            from,
+            to,
+            authority,
+            amount,
+        } => token_transfer(ctx, from, to, authority, *amount),
+        Instruction::MintTo {
+            mint,
+            to,
+            authority,
+            amount,
+        } => mint_to(ctx, mint, to, authority, *amount),
+        Instruction::Burn {
+            from,
+            mint,
+            authority,
+            amount,
+        } => burn(ctx, from, mint, authority, *amount),
+        Instruction::DelegateStake {
+            from,
+            stake,
+            validator,
+            amount,
+        } => delegate_stake(ctx, from, stake, validator, *amount),
+        Instruction::DeactivateStake { stake } => deactivate_stake(ctx, stake),
+        Instruction::WithdrawStake { stake, to, amount } => withdraw_stake(ctx, stake, to, *amount),
+        Instruction::Invoke {
+            program,
+            accounts,
+            signers,
+            data,
+        } => invoke(ctx, program, accounts, signers, data),
+    }
+}
+
+/// Moves the transaction fee out of the `payer` wallet, into the `collector`
+/// wallet or out of the supply when there is none.
+pub fn charge_fee(
+    ctx: &mut TransactionContext,
+    payer: &Pubkey,
+    fee: u64,
+    collector: Option<&Pubkey>,
+) -> Result<(), LedgerError> {
+    let payer_wallet = ctx.get(payer)?;
+    if payer_wallet.kind() != AccountKind::Wallet {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "fee payer {} is not a Wallet",
+            payer
+        )));
+    }
+    if payer_wallet.lamports < fee {
+        return Err(LedgerError::InsufficientFunds {
+            require: fee,
+            available: payer_wallet.lamports,
+        });
+    }
+    if let Some(collector) = collector
+        && ctx.get(collector)?.kind() != AccountKind::Wallet
+    {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "fee collector {} is not a Wallet",
+            collector
+        )));
+    }
+
+    debit(ctx.get_mut(payer)?, fee)?;
+    match collector {
+        Some(collector) => credit(ctx.get_mut(collector)?, fee)?,
+        None => ctx.burned = checked_add(ctx.burned, fee)?,
+    }
+
+    Ok(())
+}
+
+fn transfer(
+    ctx: &mut TransactionContext,
+    from: &Pubkey,
+    to: &Pubkey,
+    amount: u64,
+) -> Result<(), LedgerError> {
+    let from_wallet = ctx.get(from)?;
+    let to_wallet = ctx.get(to)?;
+    if from_wallet.kind() != AccountKind::Wallet {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Wallet",
+            from
+        )));
+    }
+    if to_wallet.kind() != AccountKind::Wallet {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Wallet",
+            to
+        )));
+    }
+    if from_wallet.lamports < amount {
+        return Err(LedgerError::InsufficientFunds {
+            require: amount,
+            available: from_wallet.lamports,
+        });
+    }
+
+    debit(ctx.get_mut(from)?, amount)?;
+    credit(ctx.get_mut(to)?, amount)?;
+
+    Ok(())
+}
+
+fn create_account(ctx: &mut TransactionContext, account: &Account) -> Result<(), LedgerError> {
+    if ctx.get(&account.pubkey).is_ok() {
+        return Err(LedgerError::DuplicateAccount(account.pubkey.to_string()));
+    }
+    ctx.rent.check_exempt(account)?;
+
+    ctx.load(&account.pubkey, Some(account.clone()));
+    Ok(())
+}
+
+/// Creates `account` with its lamports moved out of the fee payer wallet, an
+/// account without lamports needs no payer.
+fn fund_account(ctx: &mut TransactionContext, account: &Account) -> Result<(), LedgerError> {
+    check_new_account(ctx, account)?;
+    if account.lamports > 0 {
+        let payer = ctx
+            .payer
+            .filter(|payer| *payer != account.pubkey)
+            .ok_or_else(|| {
+                LedgerError::InvalidTransfer(format!(
+                    "key: {} must be funded by the fee payer",
+                    account.pubkey
+                ))
+            })?;
+        let payer_wallet = ctx.get_mut(&payer)?;
+        if payer_wallet.kind() != AccountKind::Wallet {
+            return Err(LedgerError::InvalidTransfer(format!(
+                "fee payer {} is not a Wallet",
+                payer
+            )));
+        }
+        debit(payer_wallet, account.lamports)?;
+    }
+
+    create_account(ctx, account)
+}
+
+/// Accounts created by a transaction start empty: tokens, mint supply and
+/// stake delegations only come from their own instructions, and program
+/// state only from the program owning it.
+fn check_new_account(ctx: &TransactionContext, account: &Account) -> Result<(), LedgerError> {
+    let reason = match &account.account_type {
+        AccountType::TokenAccount { token_balance, .. } if *token_balance > 0 => {
+            String::from("it must start without tokens")
+        }
+        AccountType::TokenAccount { mint, .. }
+            if ctx.get(mint).map(|mint| mint.kind()).ok() != Some(AccountKind::Mint) =>
+        {
+            format!("its mint {} is not a Mint", mint)
+        }
+        AccountType::Mint { supply, .. } if *supply > 0 => {
+            String::from("a new mint must start without supply")
+        }
+        AccountType::Stake {
+            activation_epoch,
+            deactivation_epoch,
+            ..
+        } if activation_epoch.is_some() || deactivation_epoch.is_some() => {
+            String::from("stake is only delegated by a DelegateStake instruction")
+        }
+        AccountType::Program {
+            executable: false,
+            program_data,
+        } if *account.owner() != BPF_LOADER_ID && program_data.iter().any(|byte| *byte != 0) => {
+            String::from("the state of a program must start zeroed")
+        }
+        _ => return Ok(()),
+    };
+
+    Err(LedgerError::InvalidNewAccount {
+        pubkey: account.pubkey.to_string(),
+        reason,
+    })
+}
+
+fn close_account(
+    ctx: &mut TransactionContext,
+    pubkey: &Pubkey,
+    destination: &Pubkey,
+) -> Result<(), LedgerError> {
+    if pubkey == destination {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} can not be closed into itself",
+            pubkey
+        )));
+    }
+
+    let account = ctx.get(pubkey)?;
+    check_closable(pubkey, account, ctx.epoch)?;
+    let lamports = account.lamports;
+    let destination_wallet = ctx.get_mut(destination)?;
+    if destination_wallet.kind() != AccountKind::Wallet {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Wallet",
+            destination
+        )));
+    }
+
+    credit(destination_wallet, lamports)?;
+    ctx.load(pubkey, None);
+    ctx.closures.push(AccountClosure {
+        pubkey: *pubkey,
+        destination: *destination,
+        lamports,
+        epoch: ctx.epoch,
+    });
+
+    Ok(())
+}
+
+/// Rejects closing an account that still holds tokens, a mint with tokens in
+/// circulation and a stake that is not cooled down.
+fn check_closable(pubkey: &Pubkey, account: &Account, epoch: u64) -> Result<(), LedgerError> {
+    match account.account_type {
+        AccountType::TokenAccount { token_balance, .. } if token_balance > 0 => {
+            Err(LedgerError::AccountNotEmpty {
+                pubkey: pubkey.to_string(),
+                reason: format!("it holds {} tokens", token_balance),
+            })
+        }
+        AccountType::Mint { supply, .. } if supply > 0 => Err(LedgerError::AccountNotEmpty {
+            pubkey: pubkey.to_string(),
+            reason: format!("{} of its tokens are in circulation", supply),
+        }),
+        AccountType::Stake {
+            staked_amount,
+            activation_epoch,
+            deactivation_epoch,
+            ..
+        } => {
+            let activation = StakeActivation::at_epoch(
+                staked_amount,
+                activation_epoch,
+                deactivation_epoch,
+                epoch,
+            );
+            if activation.status.can_withdraw() {
+                Ok(())
+            } else {
+                Err(LedgerError::StakeStillActive {
+                    pubkey: pubkey.to_string(),
+                    status: activation.status.to_string(),
+                })
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+fn token_transfer(
+    ctx: &mut TransactionContext,
+    from: &Pubkey,
+    to: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Result<(), LedgerError> {
+    let from_token = token_state(ctx.get(from)?)?;
+    let to_token = token_state(ctx.get(to)?)?;
+    from_token.check_authority(from, authority)?;
+    if from_token.mint != to_token.mint {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "mint mismatch: {} holds {}, {} holds {}",
+            from, from_token.mint, to, to_token.mint
+        )));
+    }
+    if from_token.balance < amount {
+        return Err(LedgerError::InsufficientFunds {
+            require: amount,
+            available: from_token.balance,
+        });
+    }
+
+    set_token_balance(ctx.get_mut(from)?, from_token.balance - amount);
+    let to_balance = token_state(ctx.get(to)?)?.balance;
+    set_token_balance(ctx.get_mut(to)?, checked_add(to_balance, amount)?);
+
+    Ok(())
+}
+
+fn mint_to(
+    ctx: &mut TransactionContext,
+    mint: &Pubkey,
+    to: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Result<(), LedgerError> {
+    let to_token = token_state(ctx.get(to)?)?;
+    if to_token.mint != *mint {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "mint mismatch: {} holds {}, not {}",
+            to, to_token.mint, mint
+        )));
+    }
+
+    let mint_account = ctx.get_mut(mint)?;
+    let AccountType::Mint {
+        ref mut supply,
+        ref mint_authority,
+        ..
+    } = mint_account.account_type
+    else {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Mint",
+            mint
+        )));
+    };
+    if mint_authority.as_ref() != Some(authority) {
+        return Err(LedgerError::InvalidAuthority(format!(
+            "{} is not the mint authority of {}",
+            authority, mint
+        )));
+    }
+    *supply = checked_add(*supply, amount)?;
+
+    set_token_balance(ctx.get_mut(to)?, checked_add(to_token.balance, amount)?);
+
+    Ok(())
+}
+
+fn burn(
+    ctx: &mut TransactionContext,
+    from: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Result<(), LedgerError> {
+    let from_token = token_state(ctx.get(from)?)?;
+    from_token.check_authority(from, authority)?;
+    if from_token.mint != *mint {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "mint mismatch: {} holds {}, not {}",
+            from, from_token.mint, mint
+        )));
+    }
+    if from_token.balance < amount {
+        return Err(LedgerError::InsufficientFunds {
+            require: amount,
+            available: from_token.balance,
+        });
+    }
+
+    let mint_account = ctx.get_mut(mint)?;
+    let AccountType::Mint { ref mut supply, .. } = mint_account.account_type else {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Mint",
+            mint
+        )));
+    };
+    // the tokens held never exceed the supply, a mint short of them is
+    // inconsistent and burning from it must not hide that
+    *supply = supply.checked_sub(amount).ok_or_else(|| {
+        LedgerError::InvariantViolation(format!(
+            "mint {} supply {} is below the {} tokens burned",
+            mint, supply, amount
+        ))
+    })?;
+
+    set_token_balance(ctx.get_mut(from)?, from_token.balance - amount);
+
+    Ok(())
+}
+
+fn delegate_stake(
+    ctx: &mut TransactionContext,
+    from: &Pubkey,
+    stake: &Pubkey,
+    validator: &Pubkey,
+    amount: u64,
+) -> Result<(), LedgerError> {
+    let from_wallet = ctx.get(from)?;
+    if from_wallet.kind() != AccountKind::Wallet {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Wallet",
+            from
+        )));
+    }
+    if from_wallet.lamports < amount {
+        return Err(LedgerError::InsufficientFunds {
+            require: amount,
+            available: from_wallet.lamports,
+        });
+    }
+
+    let stake_account = Account::with_pubkey(
+        *stake,
+        AccountType::Stake {
+            validator: *validator,
+            staked_amount: amount,
+            activation_epoch: Some(ctx.epoch),
+            deactivation_epoch: None,
+        },
+    );
+    create_account(ctx, &stake_account)?;
+    debit(ctx.get_mut(from)?, amount)?;
+
+    Ok(())
+}
+
+fn deactivate_stake(ctx: &mut TransactionContext, stake: &Pubkey) -> Result<(), LedgerError> {
+    let epoch = ctx.epoch;
+    let stake_account = ctx.get_mut(stake)?;
+    let AccountType::Stake {
+        ref activation_epoch,
+        ref mut deactivation_epoch,
+        ..
+    } = stake_account.account_type
+    else {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Stake",
+            stake
+        )));
+    };
+    if activation_epoch.is_none() {
+        return Err(LedgerError::StakeNotDelegated(stake.to_string()));
+    }
+    if deactivation_epoch.is_some() {
+        return Err(LedgerError::StakeAlreadyDeactivated(stake.to_string()));
+    }
+
+    *deactivation_epoch = Some(epoch);
+    Ok(())
+}
+
+fn withdraw_stake(
+    ctx: &mut TransactionContext,
+    stake: &Pubkey,
+    to: &Pubkey,
+    amount: u64,
+) -> Result<(), LedgerError> {
+    if ctx.get(to)?.kind() != AccountKind::Wallet {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Wallet",
+            to
+        )));
+    }
+
+    let epoch = ctx.epoch;
+    let stake_account = ctx.get_mut(stake)?;
+    let AccountType::Stake {
+        ref mut staked_amount,
+        activation_epoch,
+        deactivation_epoch,
+        ..
+    } = stake_account.account_type
+    else {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Stake",
+            stake
+        )));
+    };
+    let activation =
+        StakeActivation::at_epoch(*staked_amount, activation_epoch, deactivation_epoch, epoch);
+    if !activation.status.can_withdraw() {
+        return Err(LedgerError::StakeStillActive {
+            pubkey: stake.to_string(),
+            status: activation.status.to_string(),
+        });
+    }
+    if stake_account.lamports < amount {
+        return Err(LedgerError::InsufficientFunds {
+            require: amount,
+            available: stake_account.lamports,
+        });
+    }
+
+    *staked_amount = staked_amount.saturating_sub(amount);
+    stake_account.lamports -= amount;
+    credit(ctx.get_mut(to)?, amount)?;
+
+    Ok(())
+}
+
+/// Runs `program` over copies of `accounts`, stored back once it succeeded.
+/// The accounts it debited or wrote must still be rent exempt.
+fn invoke(
+    ctx: &mut TransactionContext,
+    program: &Pubkey,
+    accounts: &[Pubkey],
+    signers: &[Pubkey],
+    data: &[u8],
+) -> Result<(), LedgerError> {
+    let AccountType::Program {
+        executable: true,
+        program_data: code,
+    } = &ctx.get(program)?.account_type
+    else {
+        return Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not an executable Program",
+            program
+        )));
+    };
+    let code = code.clone();
+
+    let mut loaded = Vec::with_capacity(accounts.len());
+    for (index, pubkey) in accounts.iter().enumerate() {
+        if accounts[..index].contains(pubkey) {
+            return Err(LedgerError::InvalidTransfer(format!(
+                "key: {} is passed to {} more than once",
+                pubkey, program
+            )));
+        }
+        loaded.push(ProgramAccount {
+            account: ctx.get(pubkey)?.clone(),
+            is_signer: signers.contains(pubkey),
+        });
+    }
+
+    vm::execute(program, &code, &mut loaded, data, COMPUTE_BUDGET)?;
+
+    // programs only write the accounts they own, those and every account
+    // they debited must stay rent exempt
+    for ProgramAccount { account, .. } in loaded {
+        if account.owner() == program || account.lamports < ctx.get(&account.pubkey)?.lamports {
+            ctx.rent.check_exempt(&account)?;
+        }
+        ctx.load(&account.pubkey.clone(), Some(account));
+    }
+
+    Ok(())
+}
+
+struct TokenState {
+    mint: Pubkey,
+    balance: u64,
+    delegate: Option<Pubkey>,
+}
+
+impl TokenState {
+    /// token accounts are controlled by their own key or by their delegate
+    fn check_authority(&self, pubkey: &Pubkey, authority: &Pubkey) -> Result<(), LedgerError> {
+        if authority != pubkey && self.delegate.as_ref() != Some(authority) {
+            return Err(LedgerError::InvalidAuthority(format!(
+                "{} is neither {} nor its delegate",
+                authority, pubkey
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn token_state(account: &Account) -> Result<TokenState, LedgerError> {
+    match &account.account_type {
+        AccountType::TokenAccount {
+            mint,
+            token_balance,
+            delegate,
+        } => Ok(TokenState {
+            mint: *mint,
+            balance: *token_balance,
+            delegate: *delegate,
+        }),
+        _ => Err(LedgerError::InvalidTransfer(format!(
+            "key: {} is not a Token Account",
+            account.pubkey
+        ))),
+    }
+}
+
+fn set_token_balance(account: &mut Account, balance: u64) {
+    if let AccountType::TokenAccount {
+        ref mut token_balance,
+        ..
+    } = account.account_type
+    {
+        *token_balance = balance;
+    }
+}
+
+pub(crate) fn debit(wallet: &mut Account, amount: u64) -> Result<(), LedgerError> {
+    let insufficient = LedgerError::InsufficientFunds {
+        require: amount,
+        available: wallet.lamports,
+    };
+    let Some(lamports) = wallet.lamports.checked_sub(amount) else {
+        return Err(insufficient);
+    };
+    if let AccountType::Wallet { ref mut balance } = wallet.account_type {
+        *balance = balance.checked_sub(amount).ok_or(insufficient)?;
+    }
+    wallet.lamports = lamports;
+
+    Ok(())
+}
+
+pub(crate) fn credit(wallet: &mut Account, amount: u64) -> Result<(), LedgerError> {
+    let lamports = checked_add(wallet.lamports, amount)?;
+    if let AccountType::Wallet { ref mut balance } = wallet.account_type {
+        *balance = checked_add(*balance, amount)?;
+    }
+    wallet.lamports = lamports;
+
+    Ok(())
+}
+
+pub fn checked_add(value: u64, amount: u64) -> Result<u64, LedgerError> {
+    value
+        .checked_add(amount)
+        .ok_or(LedgerError::ArithmeticOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(accounts: &[&Account]) -> TransactionContext {
+        let mut ctx = TransactionContext::new(0, Rent::free());
+        for account in accounts {
+            ctx.load(&account.pubkey, Some((*account).clone()));
+        }
+        ctx
+    }
+
+    #[test]
+    fn test_burn_rejects_supply_mismatch() {
+        let mint = Account::new(AccountType::Mint {
+            decimals: 0,
+            supply: 5,
+            mint_authority: None,
+            freeze_authority: None,
+        });
+        // holds more tokens than the mint ever issued
+        let token = Account::new(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 8,
+            delegate: None,
+        });
+        let mut ctx = context(&[&mint, &token]);
+        let burn = |amount| Instruction::Burn {
+            from: token.pubkey,
+            mint: mint.pubkey,
+            authority: token.pubkey,
+            amount,
+        };
+
+        process_instruction(&mut ctx, &burn(3)).unwrap();
+        let err = process_instruction(&mut ctx, &burn(3)).unwrap_err();
+        assert!(matches!(err, LedgerError::InvariantViolation(_)));
+    }
+
+    #[test]
+    fn test_create_account_funded_by_payer() {
+        let payer = Account::new(AccountType::Wallet { balance: 100 });
+        let create = |balance| Instruction::CreateAccount {
+            account: Account::new(AccountType::Wallet { balance }),
+        };
+
+        let mut ctx = context(&[&payer]);
+        process_instruction(&mut ctx, &create(0)).unwrap();
+        let err = process_instruction(&mut ctx, &create(60)).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+
+        let mut ctx = context(&[&payer]).with_payer(Some(payer.pubkey));
+        process_instruction(&mut ctx, &create(60)).unwrap();
+        let err = process_instruction(&mut ctx, &create(60)).unwrap_err();
+        assert!(matches!(err, LedgerError::InsufficientFunds { .. }));
+        let lamports: u64 = ctx
+            .into_accounts()
+            .values()
+            .flatten()
+            .map(|account| account.lamports)
+            .sum();
+        assert_eq!(lamports, 100);
+    }
+
+    #[test]
+    fn test_create_account_starts_empty() {
+        let payer = Account::new(AccountType::Wallet {
+            balance: 100_000_000,
+        });
+        let mint_type = |supply| AccountType::Mint {
+            decimals: 0,
+            supply,
+            mint_authority: None,
+            freeze_authority: None,
+        };
+        let mint = Account::new(mint_type(0));
+        let token = |mint, token_balance| {
+            Account::new(AccountType::TokenAccount {
+                mint,
+                token_balance,
+                delegate: None,
+            })
+        };
+        let stake = |activation_epoch| {
+            Account::new(AccountType::Stake {
+                validator: Pubkey::new_unique(),
+                staked_amount: 10,
+                activation_epoch,
+                deactivation_epoch: None,
+            })
+        };
+        let state = |program_data| {
+            Account::new(AccountType::Program {
+                executable: false,
+                program_data,
+            })
+            .owned_by(Pubkey::new_unique())
+        };
+        let create = |account: &Account| {
+            let mut ctx = context(&[&payer, &mint]).with_payer(Some(payer.pubkey));
+            process_instruction(
+                &mut ctx,
+                &Instruction::CreateAccount {
+                    account: account.clone(),
+                },
+            )
+        };
+
+        for account in [
+            token(mint.pubkey, 5),
+            token(payer.pubkey, 0),
+            token(Pubkey::new_unique(), 0),
+            Account::new(mint_type(5)),
+            stake(Some(0)),
+            state(vec![0, 7]),
+        ] {
+            let err = create(&account).unwrap_err();
+            assert!(
+                matches!(err, LedgerError::InvalidNewAccount { .. }),
+                "{:?}",
+                account.account_type
+            );
+        }
+        for account in [
+            token(mint.pubkey, 0),
+            Account::new(mint_type(0)),
+            stake(None),
+            state(vec![0; 8]),
+        ] {
+            create(&account).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_transfer_fee_and_close() {
+        let from = Account::new(AccountType::Wallet { balance: 100 });
+        let to = Account::new(AccountType::Wallet { balance: 0 });
+        let mint = Account::new(AccountType::Mint {
+            decimals: 0,
+            supply: 0,
+            mint_authority: None,
+            freeze_authority: None,
+        });
+        let mut ctx = context(&[&from, &to, &mint]);
+        let transfer = |to: &Account, amount| Instruction::Transfer {
+            from: from.pubkey,
+            to: to.pubkey,
+            amount,
+        };
+
+        process_instruction(&mut ctx, &transfer(&to, 60)).unwrap();
+        let err = process_instruction(&mut ctx, &transfer(&to, 60)).unwrap_err();
+        assert!(matches!(
+            err,
+            LedgerError::InsufficientFunds {
+                require: 60,
+                available: 40
+            }
+        ));
+        let err = process_instruction(&mut ctx, &transfer(&mint, 1)).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+
+        charge_fee(&mut ctx, &from.pubkey, 10, None).unwrap();
+        charge_fee(&mut ctx, &from.pubkey, 10, Some(&to.pubkey)).unwrap();
+        assert_eq!(ctx.burned(), 10);
+
+        let close = |pubkey, destination| Instruction::CloseAccount {
+            pubkey,
+            destination,
+        };
+        let err = process_instruction(&mut ctx, &close(to.pubkey, to.pubkey)).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidTransfer(_)));
+        process_instruction(&mut ctx, &close(mint.pubkey, to.pubkey)).unwrap();
+        assert_eq!(
+            ctx.closures(),
+            [AccountClosure {
+                pubkey: mint.pubkey,
+                destination: to.pubkey,
+                lamports: mint.lamports,
+                epoch: 0,
+            }]
+        );
+
+        let accounts = ctx.into_accounts();
+        assert_eq!(accounts[&from.pubkey].as_ref().unwrap().lamports, 20);
+        assert_eq!(
+            accounts[&to.pubkey].as_ref().unwrap().lamports,
+            70 + mint.lamports
+        );
+        assert!(accounts[&mint.pubkey].is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::pkg::{
+    account::{Account, AccountKind, AccountType},
+    names::{AccountRef, NameRegistry},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccountOrder {
+    #[default]
+    Pubkey,
+    Lamports,
+    CreatedAt,
+}
+
+/// Composable account filter, every criteria set must match. Results are
+/// sorted with `order_by` before `offset` and `limit` paginate them. Keys are
+/// given by pubkey or by name, names only match once resolved with
+/// `resolve_names`.
+#[derive(Debug, Clone)]
+pub struct AccountQuery {
+    kind: Option<AccountKind>,
+    owner: Option<AccountRef>,
+    lamports: (Bound<u64>, Bound<u64>),
+    mint: Option<AccountRef>,
+    validator: Option<AccountRef>,
+    created_at: (Bound<u64>, Bound<u64>),
+    order: AccountOrder,
+    descending: bool,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Default for AccountQuery {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            owner: None,
+            lamports: (Bound::Unbounded, Bound::Unbounded),
+            mint: None,
+            validator: None,
+            created_at: (Bound::Unbounded, Bound::Unbounded),
+            order: AccountOrder::default(),
+            descending: false,
+            offset: 0,
+            limit: None,
+        }
+    }
+}
+
+impl AccountQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn kind(mut self, kind: AccountKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn owner(mut self, owner: impl Into<AccountRef>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    pub fn lamports(mut self, range: impl RangeBounds<u64>) -> Self {
+        self.lamports = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// only token accounts of this mint
+    pub fn mint(mut self, mint: impl Into<AccountRef>) -> Self {
+        self.mint = Some(mint.into());
+        self
+    }
+
+    /// only stake accounts delegated to this validator
+    pub fn validator(mut self, validator: impl Into<AccountRef>) -> Self {
+        self.validator = Some(validator.into());
+        self
+    }
+
+    /// creation time range in seconds since UNIX EPOCH
+    pub fn created_at(mut self, range: impl RangeBounds<u64>) -> Self {
+        self.created_at = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    pub fn order_by(mut self, order: AccountOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn kind_filter(&self) -> Option<AccountKind> {
+        self.kind
+    }
+
+    /// Same query with the names it filters on replaced by the keys they
+    /// name in `names`. Unknown names are kept and match no account.
+    pub fn resolve_names(&self, names: &NameRegistry) -> Self {
+        let resolve = |filter: &Option<AccountRef>| {
+            filter.as_ref().map(|account| {
+                names
+                    .resolve_ref(account)
+                    .map(AccountRef::Pubkey)
+                    .unwrap_or_else(|| account.clone())
+            })
+        };
+
+        Self {
+            owner: resolve(&self.owner),
+            mint: resolve(&self.mint),
+            validator: resolve(&self.validator),
+            ..self.clone()
+        }
+    }
+
+    pub fn matches(&self, account: &Account) -> bool {
+        if self.kind.is_some_and(|kind| kind != account.kind()) {
+            return false;
+        }
+        if self
+            .owner
+            .as_ref()
+            .is_some_and(|owner| !is_key(owner, account.owner()))
+        {
+            return false;
+        }
+        if !self.lamports.contains(&account.lamports)
+            || !self.created_at.contains(&account.created_at())
+        {
+            return false;
+        }
+        if let Some(mint_filter) = &self.mint {
+            match &account.account_type {
+                AccountType::TokenAccount { mint, .. } if is_key(mint_filter, mint) => {}
+                _ => return false,
+            }
+        }
+        if let Some(validator_filter) = &self.validator {
+            match &account.account_type {
+                AccountType::Stake { validator, .. } if is_key(validator_filter, validator) => {}
+                _ => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Filters, sorts and paginates `accounts`.
+    pub fn apply<'a>(&self, accounts: impl Iterator<Item = &'a Account>) -> Vec<&'a Account> {
+        let mut result: Vec<&Account> = accounts.filter(|acc| self.matches(acc)).collect();
+        result.sort_by(|a, b| {
+            let ordering = match self.order {
+                AccountOrder::Pubkey => Ordering::Equal,
+                AccountOrder::Lamports => a.lamports.cmp(&b.lamports),
+                AccountOrder::CreatedAt => a.created_at().cmp(&b.created_at()),
+            }
+            .then_with(|| a.pubkey.cmp(&b.pubkey));
+
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        result
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Whether `account` is `key`, an unresolved name is no key.
+fn is_key(account: &AccountRef, key: &Pubkey) -> bool {
+    matches!(account, AccountRef::Pubkey(pubkey) if pubkey == key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::account::{STAKE_PROGRAM_ID, SYSTEM_PROGRAM_ID};
+
+    #[test]
+    fn test_query_filters_orders_and_paginates() {
+        let validator = Pubkey::new_unique();
+        let wallets: Vec<Account> = [30, 10, 20]
+            .into_iter()
+            .map(|balance| Account::new(AccountType::Wallet { balance }))
+            .collect();
+        let stake = Account::new(AccountType::Stake {
+            validator,
+            staked_amount: 15,
+            activation_epoch: None,
+            deactivation_epoch: None,
+        });
+        let accounts: Vec<&Account> = wallets.iter().chain([&stake]).collect();
+        let lamports =
+            |result: Vec<&Account>| result.iter().map(|acc| acc.lamports).collect::<Vec<_>>();
+
+        let by_lamports = AccountQuery::new()
+            .kind(AccountKind::Wallet)
+            .order_by(AccountOrder::Lamports);
+        assert_eq!(
+            lamports(by_lamports.apply(accounts.iter().copied())),
+            [10, 20, 30]
+        );
+        let page = by_lamports.clone().descending().offset(1).limit(1);
+        assert_eq!(lamports(page.apply(accounts.iter().copied())), [20]);
+
+        let query = AccountQuery::new()
+            .owner(SYSTEM_PROGRAM_ID)
+            .lamports(15..=25);
+        assert_eq!(lamports(query.apply(accounts.iter().copied())), [20]);
+        assert!(
+            AccountQuery::new()
+                .owner(STAKE_PROGRAM_ID)
+                .validator(validator)
+                .matches(&stake)
+        );
+        assert!(!AccountQuery::new().mint(validator).matches(&stake));
+        assert_eq!(by_lamports.kind_filter(), Some(AccountKind::Wallet));
+
+        // names only match once resolved
+        let mut names = NameRegistry::new();
+        names.register("validator", validator).unwrap();
+        let by_name = AccountQuery::new().validator("Validator");
+        assert!(!by_name.matches(&stake));
+        assert!(by_name.resolve_names(&names).matches(&stake));
+    }
+}
@@ -0,0 +1,151 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::pkg::{
+    account::{Account, AccountType},
+    errors::LedgerError,
+};
+
+/// Bytes charged for every account on top of its data, the key, owner and
+/// lamports stored alongside it.
+pub const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+
+/// Rent schedule, accounts pay for the bytes they hold unless their balance
+/// covers `exemption_threshold_years` of rent.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize, Serialize, Deserialize,
+)]
+pub struct Rent {
+    pub lamports_per_byte_year: u64,
+    pub exemption_threshold_years: u64,
+    /// collection passes per year, each one charges its share of a year
+    pub epochs_per_year: u64,
+}
+
+impl Default for Rent {
+    /// Same rates as Solana mainnet.
+    fn default() -> Self {
+        Self {
+            lamports_per_byte_year: 3_480,
+            exemption_threshold_years: 2,
+            epochs_per_year: 182,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RentCollection {
+    /// lamports charged, they are burned and leave the total supply
+    pub collected: u64,
+    /// accounts closed because they could not pay
+    pub closed: Vec<Pubkey>,
+}
+
+impl Rent {
+    /// Schedule where storage costs nothing and every account is exempt.
+    pub fn free() -> Self {
+        Self {
+            lamports_per_byte_year: 0,
+            exemption_threshold_years: 0,
+            epochs_per_year: 1,
+        }
+    }
+
+    /// Lamports an account holding `data_len` bytes needs to be rent exempt.
+    pub fn minimum_balance(&self, data_len: usize) -> u64 {
+        self.yearly_rent(data_len)
+            .saturating_mul(self.exemption_threshold_years)
+    }
+
+    pub fn is_exempt(&self, lamports: u64, data_len: usize) -> bool {
+        lamports >= self.minimum_balance(data_len)
+    }
+
+    /// Rent a non-exempt account holding `data_len` bytes pays each epoch.
+    pub fn due(&self, data_len: usize) -> u64 {
+        self.yearly_rent(data_len) / self.epochs_per_year.max(1)
+    }
+
+    /// Rejects new accounts that would start below the exemption minimum.
+    pub fn check_exempt(&self, account: &Account) -> Result<(), LedgerError> {
+        let minimum = self.minimum_balance(account.data_len());
+        if account.lamports < minimum {
+            return Err(LedgerError::RentNotExempt {
+                pubkey: account.pubkey.to_string(),
+                minimum,
+                lamports: account.lamports,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Lamports one epoch of rent takes from `account`, nothing when it is
+    /// exempt and never more than it holds.
+    pub fn charge(&self, account: &Account) -> u64 {
+        let data_len = account.data_len();
+        if self.is_exempt(account.lamports, data_len) {
+            return 0;
+        }
+
+        self.due(data_len).min(account.lamports)
+    }
+
+    /// Charges one epoch of rent to a non-exempt account and returns the
+    /// lamports taken, see `charge`.
+    pub fn collect(&self, account: &mut Account) -> u64 {
+        let charged = self.charge(account);
+        account.lamports -= charged;
+        if let AccountType::Wallet { ref mut balance } = account.account_type {
+            *balance = balance.saturating_sub(charged);
+        }
+
+        charged
+    }
+
+    fn yearly_rent(&self, data_len: usize) -> u64 {
+        (ACCOUNT_STORAGE_OVERHEAD + data_len as u64).saturating_mul(self.lamports_per_byte_year)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rent_exemption_and_collection() {
+        let rent = Rent {
+            lamports_per_byte_year: 2,
+            exemption_threshold_years: 2,
+            epochs_per_year: 4,
+        };
+        let mut wallet = Account::new(AccountType::Wallet { balance: 100 });
+        let data_len = wallet.data_len();
+        let yearly = (ACCOUNT_STORAGE_OVERHEAD + data_len as u64) * 2;
+        assert_eq!(rent.minimum_balance(data_len), yearly * 2);
+        assert_eq!(rent.due(data_len), yearly / 4);
+        assert!(matches!(
+            rent.check_exempt(&wallet),
+            Err(LedgerError::RentNotExempt { lamports: 100, .. })
+        ));
+
+        let charged = rent.collect(&mut wallet);
+        assert_eq!(charged, (yearly / 4).min(100));
+        assert_eq!(wallet.lamports, 100 - charged);
+        assert!(
+            matches!(wallet.account_type, AccountType::Wallet { balance } if balance == wallet.lamports)
+        );
+
+        // never charges more than the account holds
+        wallet.lamports = 1;
+        assert_eq!(rent.charge(&wallet), 1);
+
+        let exempt = Account::new(AccountType::Wallet {
+            balance: yearly * 2,
+        });
+        assert!(rent.check_exempt(&exempt).is_ok());
+        assert_eq!(rent.charge(&exempt), 0);
+        assert_eq!(Rent::free().minimum_balance(1_000), 0);
+    }
+}
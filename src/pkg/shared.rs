@@ -0,0 +1,212 @@
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::{Arc, Condvar, Mutex, RwLock},
+};
+
+use crate::pkg::{
+    account::Account,
+    errors::LedgerError,
+    ledger::Ledger,
+    names::AccountRef,
+    transaction::{Instruction, Transaction},
+};
+
+/// Cloneable handle sharing one ledger between threads.
+///
+/// Transactions lock the accounts they touch, always in pubkey order so two
+/// of them can never wait on each other, and execute under a shared read
+/// lock: transactions over disjoint accounts run in parallel while reads are
+/// served. Only the commit of the executed accounts takes the ledger write
+/// lock.
+#[derive(Debug, Clone)]
+pub struct SharedLedger {
+    state: Arc<RwLock<State>>,
+    locks: Arc<AccountLocks>,
+}
+
+#[derive(Debug)]
+struct State {
+    ledger: Ledger,
+    /// bumped by every write outside of a transaction, a transaction executed
+    /// against an older generation runs again before it commits
+    generation: u64,
+}
+
+impl SharedLedger {
+    pub fn new(ledger: Ledger) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(State {
+                ledger,
+                generation: 0,
+            })),
+            locks: Arc::new(AccountLocks::default()),
+        }
+    }
+
+    /// Runs `f` with shared access to the ledger, any number of readers and
+    /// executing transactions run at once.
+    pub fn read<T>(&self, f: impl FnOnce(&Ledger) -> T) -> T {
+        let state = self.state.read().expect("ledger lock poisoned");
+        f(&state.ledger)
+    }
+
+    /// Runs `f` with exclusive access to the ledger, for mutations other than
+    /// transactions like adding accounts or advancing the epoch.
+    pub fn write<T>(&self, f: impl FnOnce(&mut Ledger) -> T) -> T {
+        let mut state = self.state.write().expect("ledger lock poisoned");
+        state.generation += 1;
+        f(&mut state.ledger)
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.read(|ledger| ledger.get_account(pubkey).cloned())
+    }
+
+    pub fn total_supply(&self) -> Result<u64, LedgerError> {
+        self.read(Ledger::total_supply)
+    }
+
+    pub fn transfer(
+        &self,
+        from: &Keypair,
+        to: impl Into<AccountRef>,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        let mut tx = Transaction::new(vec![Instruction::Transfer {
+            from: from.pubkey(),
+            to: self.read(|ledger| ledger.resolve(to))?,
+            amount,
+        }]);
+        tx.sign(from)?;
+
+        self.process_transaction(&tx)
+    }
+
+    /// Same as `Ledger::process_transaction`, waiting only for the
+    /// transactions that touch one of the same accounts. The fee collector
+    /// is one of them when the fee schedule has one.
+    pub fn process_transaction(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let mut keys: BTreeSet<Pubkey> = tx.account_keys().into_iter().collect();
+        keys.extend(self.read(|ledger| ledger.fees().collector));
+        let _guard = self.locks.lock(keys);
+
+        let (ctx, generation) = {
+            let state = self.state.read().expect("ledger lock poisoned");
+            (state.ledger.execute_transaction(tx)?, state.generation)
+        };
+
+        let mut state = self.state.write().expect("ledger lock poisoned");
+        let ctx = if state.generation == generation {
+            ctx
+        } else {
+            state.ledger.execute_transaction(tx)?
+        };
+        state.ledger.commit_transaction(tx, ctx)
+    }
+}
+
+impl From<Ledger> for SharedLedger {
+    fn from(ledger: Ledger) -> Self {
+        Self::new(ledger)
+    }
+}
+
+/// Set of the accounts locked by running transactions.
+#[derive(Debug, Default)]
+struct AccountLocks {
+    locked: Mutex<HashSet<Pubkey>>,
+    released: Condvar,
+}
+
+impl AccountLocks {
+    /// Takes the locks one key at a time in ascending order, a transaction
+    /// only ever waits on a key greater than all the ones it holds, which
+    /// rules out a cycle of waiting transactions.
+    fn lock(&self, keys: BTreeSet<Pubkey>) -> AccountGuard<'_> {
+        let mut locked = self.locked.lock().expect("account locks poisoned");
+        for key in &keys {
+            while locked.contains(key) {
+                locked = self.released.wait(locked).expect("account locks poisoned");
+            }
+            locked.insert(*key);
+        }
+
+        AccountGuard { locks: self, keys }
+    }
+}
+
+struct AccountGuard<'a> {
+    locks: &'a AccountLocks,
+    keys: BTreeSet<Pubkey>,
+}
+
+impl Drop for AccountGuard<'_> {
+    fn drop(&mut self) {
+        let mut locked = self.locks.locked.lock().expect("account locks poisoned");
+        for key in &self.keys {
+            locked.remove(key);
+        }
+        self.locks.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::account::AccountType;
+    use std::thread;
+
+    #[test]
+    fn test_shared_ledger_concurrent_transfers() {
+        let mut ledger = Ledger::new();
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
+        for keypair in &keypairs {
+            ledger
+                .add_account(Account::from_keypair(
+                    keypair,
+                    AccountType::Wallet { balance: 1_000 },
+                ))
+                .unwrap();
+        }
+        let shared = SharedLedger::new(ledger);
+        let keys: Vec<Pubkey> = keypairs.iter().map(|keypair| keypair.pubkey()).collect();
+
+        thread::scope(|scope| {
+            // every wallet sends to the next one, each pair of neighbours
+            // conflicts in both directions
+            for (index, keypair) in keypairs.iter().enumerate() {
+                let shared = shared.clone();
+                let to = keys[(index + 1) % keys.len()];
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        shared.transfer(keypair, to, 1).unwrap();
+                    }
+                });
+            }
+            let reader = shared.clone();
+            scope.spawn(move || {
+                for _ in 0..50 {
+                    assert_eq!(reader.total_supply().unwrap(), 4_000);
+                }
+            });
+            let writer = shared.clone();
+            scope.spawn(move || {
+                for _ in 0..10 {
+                    writer.write(|ledger| ledger.advance_epoch()).unwrap();
+                }
+            });
+        });
+
+        for key in &keys {
+            assert_eq!(shared.get_account(key).unwrap().lamports, 1_000);
+        }
+        assert_eq!(shared.read(|ledger| ledger.epoch()), 10);
+
+        let err = shared.transfer(&keypairs[0], keys[1], 5_000).unwrap_err();
+        assert!(matches!(err, LedgerError::InsufficientFunds { .. }));
+    }
+}
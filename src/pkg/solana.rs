@@ -0,0 +1,441 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::pkg::{
+    account::{
+        Account, AccountType, BPF_LOADER_ID, STAKE_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+        parse_pubkey,
+    },
+    errors::LedgerError,
+};
+
+/// every loader owning program accounts, imported programs are stored under
+/// `BPF_LOADER_ID`
+pub const BPF_LOADER_IDS: [Pubkey; 4] = [
+    pubkey!("BPFLoader1111111111111111111111111111111111"),
+    BPF_LOADER_ID,
+    pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"),
+    pubkey!("LoaderV411111111111111111111111111111111111"),
+];
+
+/// SPL Token account and mint sizes
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const MINT_LEN: usize = 82;
+/// offset of the delegation in a `StakeStateV2::Stake`, after the enum tag
+/// and the stake meta
+const DELEGATION_OFFSET: usize = 4 + 120;
+
+#[derive(Debug, Default)]
+pub struct SolanaImport {
+    pub accounts: Vec<Account>,
+    /// accounts with no matching account type: owned by another program, or
+    /// System accounts holding data like nonce accounts
+    pub skipped: Vec<Pubkey>,
+    /// mints of imported token accounts that are not part of the import
+    pub missing_mints: Vec<Pubkey>,
+}
+
+/// Output of `solana account --output json`.
+#[derive(Deserialize)]
+struct AccountDump {
+    pubkey: String,
+    account: UiAccount,
+}
+
+#[derive(Deserialize)]
+struct UiAccount {
+    lamports: u64,
+    /// data and its encoding
+    data: (String, String),
+    owner: String,
+    executable: bool,
+}
+
+/// Entry of a genesis primordial accounts map, keyed by pubkey.
+#[derive(Deserialize)]
+struct GenesisAccount {
+    balance: u64,
+    owner: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    executable: bool,
+}
+
+/// Reads Solana accounts from a single `solana account --output json` dump,
+/// an array of them, or a genesis accounts map. Accounts are mapped onto
+/// ledger account types by their owner program, the ones of other programs
+/// and System accounts holding data are skipped.
+pub fn import_accounts(json: &str) -> Result<SolanaImport, LedgerError> {
+    let value: Value = serde_json::from_str(json).map_err(|err| invalid("document", err))?;
+
+    let mut import = SolanaImport::default();
+    match value {
+        Value::Array(dumps) => {
+            for (index, dump) in dumps.into_iter().enumerate() {
+                import_dump(&mut import, &format!("account {}", index), dump)?;
+            }
+        }
+        Value::Object(ref object) if object.contains_key("pubkey") => {
+            import_dump(&mut import, "account", value)?;
+        }
+        Value::Object(_) => {
+            let genesis: BTreeMap<String, GenesisAccount> =
+                serde_json::from_value(value).map_err(|err| invalid("genesis", err))?;
+            for (pubkey, account) in genesis {
+                let data = STANDARD
+                    .decode(&account.data)
+                    .map_err(|err| invalid(&pubkey, format!("invalid base64 data: {}", err)))?;
+                import_account(
+                    &mut import,
+                    &pubkey,
+                    account.balance,
+                    &account.owner,
+                    data,
+                    account.executable,
+                )?;
+            }
+        }
+        _ => {
+            return Err(invalid(
+                "document",
+                "expected an account dump or a genesis map",
+            ));
+        }
+    }
+    import.missing_mints = missing_mints(&import.accounts);
+
+    Ok(import)
+}
+
+/// Mints referenced by the token accounts of `accounts` but not among them.
+fn missing_mints(accounts: &[Account]) -> Vec<Pubkey> {
+    let mints: BTreeSet<Pubkey> = accounts
+        .iter()
+        .filter(|account| matches!(account.account_type, AccountType::Mint { .. }))
+        .map(|account| account.pubkey)
+        .collect();
+    let referenced: BTreeSet<Pubkey> = accounts
+        .iter()
+        .filter_map(|account| match account.account_type {
+            AccountType::TokenAccount { mint, .. } => Some(mint),
+            _ => None,
+        })
+        .collect();
+
+    referenced.difference(&mints).copied().collect()
+}
+
+fn import_dump(import: &mut SolanaImport, location: &str, dump: Value) -> Result<(), LedgerError> {
+    let dump: AccountDump = serde_json::from_value(dump).map_err(|err| invalid(location, err))?;
+    let (data, encoding) = &dump.account.data;
+    if encoding != "base64" {
+        return Err(invalid(
+            &dump.pubkey,
+            format!("unsupported data encoding {}, dump with base64", encoding),
+        ));
+    }
+    let data = STANDARD
+        .decode(data)
+        .map_err(|err| invalid(&dump.pubkey, format!("invalid base64 data: {}", err)))?;
+
+    import_account(
+        import,
+        &dump.pubkey,
+        dump.account.lamports,
+        &dump.account.owner,
+        data,
+        dump.account.executable,
+    )
+}
+
+fn import_account(
+    import: &mut SolanaImport,
+    pubkey: &str,
+    lamports: u64,
+    owner: &str,
+    data: Vec<u8>,
+    executable: bool,
+) -> Result<(), LedgerError> {
+    let key = parse_pubkey(pubkey).map_err(|err| invalid(pubkey, err))?;
+    let owner = parse_pubkey(owner).map_err(|err| invalid(pubkey, err))?;
+
+    let Some(account_type) = account_type(&owner, data, executable, lamports)
+        .map_err(|reason| invalid(pubkey, reason))?
+    else {
+        import.skipped.push(key);
+        return Ok(());
+    };
+
+    let mut account = Account::with_pubkey(key, account_type);
+    account.lamports = lamports;
+    import.accounts.push(account);
+    Ok(())
+}
+
+fn account_type(
+    owner: &Pubkey,
+    data: Vec<u8>,
+    executable: bool,
+    lamports: u64,
+) -> Result<Option<AccountType>, String> {
+    if executable || BPF_LOADER_IDS.contains(owner) {
+        return Ok(Some(AccountType::Program {
+            executable,
+            program_data: data,
+        }));
+    }
+
+    let account_type = match *owner {
+        // nonce and other System accounts with data are not wallets
+        SYSTEM_PROGRAM_ID if !data.is_empty() => return Ok(None),
+        SYSTEM_PROGRAM_ID => AccountType::Wallet { balance: lamports },
+        TOKEN_PROGRAM_ID => match data.len() {
+            TOKEN_ACCOUNT_LEN => AccountType::TokenAccount {
+                mint: read_pubkey(&data, 0),
+                token_balance: read_u64(&data, 64),
+                delegate: Some(token_authority(&data)?),
+            },
+            MINT_LEN => AccountType::Mint {
+                mint_authority: read_option_pubkey(&data, 0),
+                supply: read_u64(&data, 36),
+                decimals: data[44],
+                freeze_authority: read_option_pubkey(&data, 46),
+            },
+            len => return Err(format!("token program data of {} bytes", len)),
+        },
+        STAKE_PROGRAM_ID => stake_account_type(&data)?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(account_type))
+}
+
+/// The SPL owner of a token account, kept as its delegate: apart from the
+/// account key itself the delegate is the one authority a ledger token
+/// account has. An SPL delegate other than the owner can not be kept along
+/// with it, the account is rejected rather than imported unspendable.
+fn token_authority(data: &[u8]) -> Result<Pubkey, String> {
+    let owner = read_pubkey(data, 32);
+    match read_option_pubkey(data, 72) {
+        Some(delegate) if delegate != owner => Err(format!(
+            "token account owned by {} also delegates to {}, only one authority can be kept",
+            owner, delegate
+        )),
+        _ => Ok(owner),
+    }
+}
+
+/// Maps a bincode `StakeStateV2`, bootstrap stakes activated at `u64::MAX`
+/// are active from epoch 0.
+fn stake_account_type(data: &[u8]) -> Result<AccountType, String> {
+    if data.len() < DELEGATION_OFFSET + 56 {
+        return Err(format!("stake program data of {} bytes", data.len()));
+    }
+
+    let not_delegated = AccountType::Stake {
+        validator: Pubkey::default(),
+        staked_amount: 0,
+        activation_epoch: None,
+        deactivation_epoch: None,
+    };
+    match u32::from_le_bytes(data[..4].try_into().unwrap()) {
+        0 | 1 => Ok(not_delegated),
+        2 => {
+            let epoch = |offset| match read_u64(data, offset) {
+                u64::MAX => None,
+                epoch => Some(epoch),
+            };
+            Ok(AccountType::Stake {
+                validator: read_pubkey(data, DELEGATION_OFFSET),
+                staked_amount: read_u64(data, DELEGATION_OFFSET + 32),
+                activation_epoch: epoch(DELEGATION_OFFSET + 40).or(Some(0)),
+                deactivation_epoch: epoch(DELEGATION_OFFSET + 48),
+            })
+        }
+        tag => Err(format!("unsupported stake state {}", tag)),
+    }
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// `COption<Pubkey>`, a `u32` tag followed by the key
+fn read_option_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    match data[offset..offset + 4] {
+        [0, 0, 0, 0] => None,
+        _ => Some(read_pubkey(data, offset + 4)),
+    }
+}
+
+fn invalid(location: &str, reason: impl ToString) -> LedgerError {
+    LedgerError::InvalidRecord {
+        location: location.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::account::AccountKind;
+
+    fn dump(pubkey: &Pubkey, lamports: u64, owner: &Pubkey, data: &[u8]) -> Value {
+        serde_json::json!({
+            "pubkey": pubkey.to_string(),
+            "account": {
+                "lamports": lamports,
+                "data": [STANDARD.encode(data), "base64"],
+                "owner": owner.to_string(),
+                "executable": false,
+                "rentEpoch": u64::MAX,
+                "space": data.len(),
+            }
+        })
+    }
+
+    #[test]
+    fn test_import_account_dumps() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut token_data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        token_data[..32].copy_from_slice(mint.as_ref());
+        token_data[32..64].copy_from_slice(owner.as_ref());
+        token_data[64..72].copy_from_slice(&42u64.to_le_bytes());
+
+        let validator = Pubkey::new_unique();
+        let mut stake_data = vec![0u8; 200];
+        stake_data[0] = 2;
+        stake_data[DELEGATION_OFFSET..DELEGATION_OFFSET + 32].copy_from_slice(validator.as_ref());
+        stake_data[DELEGATION_OFFSET + 32..DELEGATION_OFFSET + 40]
+            .copy_from_slice(&1_000u64.to_le_bytes());
+        stake_data[DELEGATION_OFFSET + 40..DELEGATION_OFFSET + 48]
+            .copy_from_slice(&5u64.to_le_bytes());
+        stake_data[DELEGATION_OFFSET + 48..DELEGATION_OFFSET + 56]
+            .copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let vote_program = pubkey!("Vote111111111111111111111111111111111111111");
+        let mut dumps = serde_json::json!([
+            dump(&Pubkey::new_unique(), 500, &SYSTEM_PROGRAM_ID, &[]),
+            dump(
+                &Pubkey::new_unique(),
+                2_039_280,
+                &TOKEN_PROGRAM_ID,
+                &token_data
+            ),
+            dump(
+                &Pubkey::new_unique(),
+                1_002_282_880,
+                &STAKE_PROGRAM_ID,
+                &stake_data
+            ),
+            dump(
+                &Pubkey::new_unique(),
+                27_074_400,
+                &vote_program,
+                &[0; 3_762]
+            ),
+        ]);
+
+        // a nonce account holds System owned data
+        let nonce = Pubkey::new_unique();
+        dumps
+            .as_array_mut()
+            .unwrap()
+            .push(dump(&nonce, 1_447_680, &SYSTEM_PROGRAM_ID, &[1; 80]));
+
+        let import = import_accounts(&dumps.to_string()).unwrap();
+        assert_eq!(import.accounts.len(), 3);
+        assert_eq!(import.skipped.len(), 2);
+        assert!(import.skipped.contains(&nonce));
+        // the mint of the token account is not part of the dump
+        assert_eq!(import.missing_mints, vec![mint]);
+
+        let kinds: Vec<AccountKind> = import.accounts.iter().map(|acc| acc.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                AccountKind::Wallet,
+                AccountKind::TokenAccount,
+                AccountKind::Stake
+            ]
+        );
+        assert_eq!(import.accounts[1].lamports, 2_039_280);
+        if let AccountType::TokenAccount {
+            mint: ref token_mint,
+            token_balance,
+            delegate: ref token_delegate,
+        } = import.accounts[1].account_type
+        {
+            assert_eq!(*token_mint, mint);
+            assert_eq!(token_balance, 42);
+            // the SPL owner can move the tokens
+            assert_eq!(*token_delegate, Some(owner));
+        } else {
+            panic!("account is not a token account");
+        }
+        if let AccountType::Stake {
+            validator: ref validator_key,
+            staked_amount,
+            activation_epoch,
+            deactivation_epoch,
+        } = import.accounts[2].account_type
+        {
+            assert_eq!(*validator_key, validator);
+            assert_eq!(staked_amount, 1_000);
+            assert_eq!(activation_epoch, Some(5));
+            assert_eq!(deactivation_epoch, None);
+        } else {
+            panic!("account is not a stake account");
+        }
+
+        let bad_token = dump(&Pubkey::new_unique(), 1, &TOKEN_PROGRAM_ID, &[0; 10]);
+        let err = import_accounts(&bad_token.to_string()).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidRecord { .. }));
+
+        token_data[72] = 1;
+        token_data[76..108].copy_from_slice(Pubkey::new_unique().as_ref());
+        let delegated = dump(&Pubkey::new_unique(), 1, &TOKEN_PROGRAM_ID, &token_data);
+        let err = import_accounts(&delegated.to_string()).unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidRecord { .. }));
+    }
+
+    #[test]
+    fn test_import_genesis_accounts() {
+        let wallet = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let genesis = serde_json::json!({
+            wallet.to_string(): {
+                "balance": 500_000_000_000u64,
+                "owner": SYSTEM_PROGRAM_ID.to_string(),
+            },
+            program.to_string(): {
+                "balance": 1_141_440,
+                "owner": BPF_LOADER_IDS[2].to_string(),
+                "data": STANDARD.encode(b"elf"),
+                "executable": true,
+            },
+        });
+
+        let import = import_accounts(&genesis.to_string()).unwrap();
+        assert_eq!(import.accounts.len(), 2);
+        for account in &import.accounts {
+            if account.pubkey == program {
+                assert_eq!(account.kind(), AccountKind::Program);
+                assert_eq!(account.lamports, 1_141_440);
+                assert_eq!(*account.owner(), BPF_LOADER_ID);
+            } else {
+                assert_eq!(account.kind(), AccountKind::Wallet);
+                assert_eq!(account.lamports, 500_000_000_000);
+            }
+        }
+    }
+}
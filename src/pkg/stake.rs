@@ -0,0 +1,135 @@
+use std::fmt::Display;
+
+/// Epochs a delegation takes to become fully effective, and to cool down
+/// once deactivated. A share of the stake warms up or cools down each epoch.
+pub const STAKE_WARMUP_EPOCHS: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakeStatus {
+    /// never delegated
+    Inactive,
+    Activating,
+    Active,
+    Deactivating,
+    /// fully cooled down, lamports can be withdrawn
+    Deactivated,
+}
+
+impl Display for StakeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StakeStatus::Inactive => write!(f, "inactive"),
+            StakeStatus::Activating => write!(f, "activating"),
+            StakeStatus::Active => write!(f, "active"),
+            StakeStatus::Deactivating => write!(f, "deactivating"),
+            StakeStatus::Deactivated => write!(f, "deactivated"),
+        }
+    }
+}
+
+impl StakeStatus {
+    pub fn can_withdraw(&self) -> bool {
+        matches!(self, StakeStatus::Inactive | StakeStatus::Deactivated)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeActivation {
+    pub status: StakeStatus,
+    /// part of the staked amount counting towards the validator this epoch
+    pub effective: u64,
+}
+
+impl StakeActivation {
+    pub fn at_epoch(
+        staked_amount: u64,
+        activation_epoch: Option<u64>,
+        deactivation_epoch: Option<u64>,
+        epoch: u64,
+    ) -> Self {
+        let Some(activation_epoch) = activation_epoch else {
+            return Self {
+                status: StakeStatus::Inactive,
+                effective: 0,
+            };
+        };
+
+        let warmed_up = warmup_share(staked_amount, epoch.saturating_sub(activation_epoch));
+        match deactivation_epoch {
+            None if warmed_up < staked_amount => Self {
+                status: StakeStatus::Activating,
+                effective: warmed_up,
+            },
+            None => Self {
+                status: StakeStatus::Active,
+                effective: staked_amount,
+            },
+            Some(deactivation_epoch) => {
+                let cooled_down =
+                    warmup_share(staked_amount, epoch.saturating_sub(deactivation_epoch));
+                let effective = warmup_share(
+                    staked_amount,
+                    deactivation_epoch.saturating_sub(activation_epoch),
+                )
+                .saturating_sub(cooled_down);
+
+                Self {
+                    status: if effective > 0 {
+                        StakeStatus::Deactivating
+                    } else {
+                        StakeStatus::Deactivated
+                    },
+                    effective,
+                }
+            }
+        }
+    }
+}
+
+fn warmup_share(staked_amount: u64, epochs: u64) -> u64 {
+    if epochs >= STAKE_WARMUP_EPOCHS {
+        return staked_amount;
+    }
+
+    (staked_amount as u128 * epochs as u128 / STAKE_WARMUP_EPOCHS as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stake_activation_warms_up_and_cools_down() {
+        let at = |deactivation_epoch, epoch| {
+            StakeActivation::at_epoch(400, Some(2), deactivation_epoch, epoch)
+        };
+
+        let inactive = StakeActivation::at_epoch(400, None, None, 10);
+        assert_eq!(inactive.status, StakeStatus::Inactive);
+        assert!(inactive.status.can_withdraw());
+
+        assert_eq!(
+            at(None, 2),
+            StakeActivation {
+                status: StakeStatus::Activating,
+                effective: 0
+            }
+        );
+        assert_eq!(at(None, 3).effective, 100);
+        assert_eq!(
+            at(None, 6),
+            StakeActivation {
+                status: StakeStatus::Active,
+                effective: 400
+            }
+        );
+
+        // deactivated halfway through the warmup, only that half cools down
+        let deactivating = at(Some(4), 5);
+        assert_eq!(deactivating.status, StakeStatus::Deactivating);
+        assert_eq!(deactivating.effective, 100);
+        assert!(!deactivating.status.can_withdraw());
+        assert_eq!(at(Some(4), 6).status, StakeStatus::Deactivated);
+        assert!(at(Some(4), 6).status.can_withdraw());
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs::{self, File, create_dir_all},
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+use crate::pkg::errors::LedgerError;
+
+/// Path of the `generation`-th previous snapshot of `path`, `1` being the
+/// most recent one.
+pub fn backup_path(path: &str, generation: usize) -> String {
+    format!("{}.{}", path, generation)
+}
+
+/// Replaces the file at `path` with `buff` so that a crash leaves either the
+/// old or the new content, never a partial file. The data is written to a
+/// temporary file next to `path`, synced, then renamed over it. When `keep`
+/// is above zero, the replaced file is kept as backup `1` and the older
+/// backups shift up to `keep`.
+pub fn write_atomic(path: &str, buff: &[u8], keep: usize) -> Result<(), LedgerError> {
+    let parent = Path::new(path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        create_dir_all(parent).map_err(io_error)?;
+    }
+
+    let tmp_path = format!("{}.tmp", path);
+    let written = File::create(&tmp_path)
+        .and_then(|mut file| file.write_all(buff).and_then(|_| file.sync_all()));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(io_error(err));
+    }
+
+    if keep > 0 && Path::new(path).exists() {
+        rotate(path, keep)?;
+    }
+    fs::rename(&tmp_path, path).map_err(io_error)?;
+    sync_dir(parent.unwrap_or(Path::new(".")))
+}
+
+/// Shifts the backups of `path` up by one, dropping the oldest, and links
+/// the current file as backup `1`. `path` itself stays in place until the
+/// new file is renamed over it.
+fn rotate(path: &str, keep: usize) -> Result<(), LedgerError> {
+    for generation in (1..keep).rev() {
+        match fs::rename(
+            backup_path(path, generation),
+            backup_path(path, generation + 1),
+        ) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(io_error(err)),
+            _ => {}
+        }
+    }
+
+    let latest = backup_path(path, 1);
+    match fs::remove_file(&latest) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(io_error(err)),
+        _ => {}
+    }
+    fs::hard_link(path, &latest)
+        .or_else(|_| fs::copy(path, &latest).map(|_| ()))
+        .map_err(io_error)
+}
+
+/// Makes the rename itself durable, directories can not be synced outside unix.
+fn sync_dir(dir: &Path) -> Result<(), LedgerError> {
+    if cfg!(unix) {
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(io_error)?;
+    }
+
+    Ok(())
+}
+
+fn io_error(err: std::io::Error) -> LedgerError {
+    LedgerError::SerializationError(err.to_string())
+}
@@ -0,0 +1,59 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+use crate::pkg::account::{Account, AccountKind, AccountType};
+
+/// Aggregate lamport and token totals, summed as `u128` so that no set of
+/// `u64` balances can overflow them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SupplyReport {
+    /// lamports held by every account
+    pub total: u128,
+    pub by_kind: BTreeMap<AccountKind, u128>,
+    /// tokens held by the token accounts of each mint
+    pub tokens: BTreeMap<Pubkey, u128>,
+}
+
+impl SupplyReport {
+    pub fn from_accounts<'a>(accounts: impl Iterator<Item = &'a Account>) -> Self {
+        let mut report = SupplyReport::default();
+        for account in accounts {
+            report.total += account.lamports as u128;
+            *report.by_kind.entry(account.kind()).or_default() += account.lamports as u128;
+            if let AccountType::TokenAccount {
+                mint,
+                token_balance,
+                ..
+            } = account.account_type
+            {
+                *report.tokens.entry(mint).or_default() += token_balance as u128;
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supply_report_does_not_overflow() {
+        let mint = Pubkey::new_unique();
+        let accounts = [
+            Account::new(AccountType::Wallet { balance: u64::MAX }),
+            Account::new(AccountType::Wallet { balance: u64::MAX }),
+            Account::new(AccountType::TokenAccount {
+                mint,
+                token_balance: u64::MAX,
+                delegate: None,
+            }),
+        ];
+
+        let report = SupplyReport::from_accounts(accounts.iter());
+        assert_eq!(report.total, 3 * u64::MAX as u128);
+        assert_eq!(report.by_kind[&AccountKind::Wallet], 2 * u64::MAX as u128);
+        assert_eq!(report.tokens[&mint], u64::MAX as u128);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::pkg::{
+    account::{Account, AccountType},
+    errors::LedgerError,
+};
+use borsh::{BorshDeserialize, BorshSerialize, to_vec};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub enum Instruction {
+    Transfer {
+        from: Pubkey,
+        to: Pubkey,
+        amount: u64,
+    },
+    CreateAccount {
+        account: Account,
+    },
+    CloseAccount {
+        pubkey: Pubkey,
+        destination: Pubkey,
+    },
+    /// `authority` is the source token account itself or its delegate
+    TokenTransfer {
+        from: Pubkey,
+        to: Pubkey,
+        authority: Pubkey,
+        amount: u64,
+    },
+    MintTo {
+        mint: Pubkey,
+        to: Pubkey,
+        authority: Pubkey,
+        amount: u64,
+    },
+    /// `authority` is the source token account itself or its delegate
+    Burn {
+        from: Pubkey,
+        mint: Pubkey,
+        authority: Pubkey,
+        amount: u64,
+    },
+    /// creates the `stake` account funded with `amount` lamports of the
+    /// `from` wallet and starts warming it up for `validator`
+    DelegateStake {
+        from: Pubkey,
+        stake: Pubkey,
+        validator: Pubkey,
+        amount: u64,
+    },
+    DeactivateStake {
+        stake: Pubkey,
+    },
+    WithdrawStake {
+        stake: Pubkey,
+        to: Pubkey,
+        amount: u64,
+    },
+    /// runs the bytecode of the executable `program` over `accounts` with
+    /// `data` as input, the program sees which of them are in `signers`
+    Invoke {
+        program: Pubkey,
+        accounts: Vec<Pubkey>,
+        signers: Vec<Pubkey>,
+        data: Vec<u8>,
+    },
+}
+
+impl Instruction {
+    /// keys of every account the instruction reads or writes
+    pub fn account_keys(&self) -> Vec<Pubkey> {
+        match self {
+            Self::Transfer { from, to, .. } => vec![*from, *to],
+            Self::CreateAccount { account } => match account.account_type {
+                AccountType::TokenAccount { mint, .. } => vec![account.pubkey, mint],
+                _ => vec![account.pubkey],
+            },
+            Self::CloseAccount {
+                pubkey,
+                destination,
+            } => vec![*pubkey, *destination],
+            Self::TokenTransfer { from, to, .. } => vec![*from, *to],
+            Self::MintTo { mint, to, .. } => vec![*mint, *to],
+            Self::Burn { from, mint, .. } => vec![*from, *mint],
+            Self::DelegateStake { from, stake, .. } => vec![*from, *stake],
+            Self::DeactivateStake { stake } => vec![*stake],
+            Self::WithdrawStake { stake, to, .. } => vec![*stake, *to],
+            Self::Invoke {
+                program, accounts, ..
+            } => std::iter::once(*program)
+                .chain(accounts.iter().copied())
+                .collect(),
+        }
+    }
+
+    /// keys that must have signed the transaction for the instruction to run
+    pub fn signers(&self) -> Vec<Pubkey> {
+        match self {
+            Self::Transfer { from, .. } => vec![*from],
+            Self::CreateAccount { account } => vec![account.pubkey],
+            Self::CloseAccount { pubkey, .. } => vec![*pubkey],
+            Self::TokenTransfer { authority, .. } => vec![*authority],
+            Self::MintTo { authority, .. } => vec![*authority],
+            Self::Burn { authority, .. } => vec![*authority],
+            Self::DelegateStake { from, stake, .. } => vec![*from, *stake],
+            Self::DeactivateStake { stake } => vec![*stake],
+            Self::WithdrawStake { stake, .. } => vec![*stake],
+            Self::Invoke { signers, .. } => signers.clone(),
+        }
+    }
+}
+
+/// A list of instructions the ledger applies all-or-nothing.
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
+pub struct Transaction {
+    pub instructions: Vec<Instruction>,
+    /// pays the transaction fees, the first signer of the instructions when unset
+    pub fee_payer: Option<Pubkey>,
+    /// lamports the fee payer adds on top of the ledger fee schedule
+    pub priority_fee: u64,
+    signatures: Vec<(Pubkey, [u8; 64])>,
+}
+
+impl Transaction {
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Self {
+            instructions,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_fee_payer(mut self, fee_payer: Pubkey) -> Self {
+        self.fee_payer = Some(fee_payer);
+        self
+    }
+
+    pub fn with_priority_fee(mut self, priority_fee: u64) -> Self {
+        self.priority_fee = priority_fee;
+        self
+    }
+
+    /// Bytes covered by the signatures, changing any instruction or fee field
+    /// after signing invalidates them.
+    pub fn message(&self) -> Result<Vec<u8>, LedgerError> {
+        to_vec(&(&self.instructions, &self.fee_payer, self.priority_fee))
+            .map_err(|err| LedgerError::SerializationError(err.to_string()))
+    }
+
+    pub fn fee_payer(&self) -> Option<Pubkey> {
+        self.fee_payer.or_else(|| self.signers().first().copied())
+    }
+
+    /// keys that must sign the transaction, the fee payer and every key the
+    /// instructions require
+    pub fn signers(&self) -> Vec<Pubkey> {
+        let mut keys: Vec<Pubkey> = self.fee_payer.into_iter().collect();
+        for key in self.instructions.iter().flat_map(|ix| ix.signers()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+
+    pub fn sign(&mut self, keypair: &Keypair) -> Result<&mut Self, LedgerError> {
+        let signature = keypair.sign_message(&self.message()?);
+        let signer = keypair.pubkey();
+
+        self.signatures.retain(|(pubkey, _)| *pubkey != signer);
+        self.signatures.push((signer, signature.into()));
+        Ok(self)
+    }
+
+    /// Checks every attached signature against the message and that the fee
+    /// payer and each key the instructions require signed it.
+    pub fn verify_signatures(&self) -> Result<(), LedgerError> {
+        let message = self.message()?;
+        for (pubkey, signature) in &self.signatures {
+            if !Signature::from(*signature).verify(pubkey.as_ref(), &message) {
+                return Err(LedgerError::InvalidSignature(pubkey.to_string()));
+            }
+        }
+
+        for signer in self.signers() {
+            if !self.signatures.iter().any(|(pubkey, _)| *pubkey == signer) {
+                return Err(LedgerError::MissingSignature(signer.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_instruction(&mut self, instruction: Instruction) -> &mut Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// keys of every account the transaction touches, the fee payer first
+    pub fn account_keys(&self) -> Vec<Pubkey> {
+        let mut keys: Vec<Pubkey> = self.fee_payer().into_iter().collect();
+        for key in self.instructions.iter().flat_map(|ix| ix.account_keys()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+}
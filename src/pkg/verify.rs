@@ -0,0 +1,247 @@
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::pkg::{
+    account::{Account, AccountType},
+    errors::LedgerError,
+};
+
+/// A broken ledger invariant, found by `Ledger::verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// an account is stored under another key than its own
+    KeyMismatch {
+        key: Pubkey,
+        pubkey: Pubkey,
+    },
+    DuplicatePubkey(Pubkey),
+    WalletBalanceMismatch {
+        pubkey: Pubkey,
+        balance: u64,
+        lamports: u64,
+    },
+    /// the lamports held differ from the ones created minus the ones burned
+    SupplyNotConserved {
+        expected: u128,
+        actual: u128,
+    },
+    TokenSupplyMismatch {
+        mint: Pubkey,
+        supply: u64,
+        held: u128,
+    },
+    StakeExceedsLamports {
+        pubkey: Pubkey,
+        staked_amount: u64,
+        lamports: u64,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KeyMismatch { key, pubkey } => {
+                write!(f, "account {} is stored under {}", pubkey, key)
+            }
+            Self::DuplicatePubkey(pubkey) => write!(f, "account {} is duplicated", pubkey),
+            Self::WalletBalanceMismatch {
+                pubkey,
+                balance,
+                lamports,
+            } => write!(
+                f,
+                "wallet {} balance {} differs from its {} lamports",
+                pubkey, balance, lamports
+            ),
+            Self::SupplyNotConserved { expected, actual } => write!(
+                f,
+                "accounts hold {} lamports, operations account for {}",
+                actual, expected
+            ),
+            Self::TokenSupplyMismatch { mint, supply, held } => write!(
+                f,
+                "mint {} supply {} differs from the {} tokens its accounts hold",
+                mint, supply, held
+            ),
+            Self::StakeExceedsLamports {
+                pubkey,
+                staked_amount,
+                lamports,
+            } => write!(
+                f,
+                "stake {} delegates {} lamports but holds {}",
+                pubkey, staked_amount, lamports
+            ),
+        }
+    }
+}
+
+/// Every violation found in one pass over the ledger, empty when the ledger
+/// is consistent.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub violations: Vec<Violation>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), LedgerError> {
+        if self.is_ok() {
+            return Ok(());
+        }
+
+        let violations: Vec<String> = self.violations.iter().map(Violation::to_string).collect();
+        Err(LedgerError::InvariantViolation(violations.join("; ")))
+    }
+}
+
+/// Checks the accounts stored by key against each other and against the
+/// `expected_supply` of lamports.
+pub fn verify_accounts<'a>(
+    accounts: impl Iterator<Item = (&'a Pubkey, &'a Account)>,
+    expected_supply: u128,
+) -> VerifyReport {
+    let mut violations = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut supply = 0u128;
+    let mut mints = BTreeMap::new();
+    let mut held: BTreeMap<Pubkey, u128> = BTreeMap::new();
+
+    for (key, account) in accounts {
+        if *key != account.pubkey {
+            violations.push(Violation::KeyMismatch {
+                key: *key,
+                pubkey: account.pubkey,
+            });
+        }
+        if !seen.insert(account.pubkey) {
+            violations.push(Violation::DuplicatePubkey(account.pubkey));
+        }
+        supply += account.lamports as u128;
+
+        match account.account_type {
+            AccountType::Wallet { balance } if balance != account.lamports => {
+                violations.push(Violation::WalletBalanceMismatch {
+                    pubkey: account.pubkey,
+                    balance,
+                    lamports: account.lamports,
+                });
+            }
+            AccountType::TokenAccount {
+                mint,
+                token_balance,
+                ..
+            } => *held.entry(mint).or_default() += token_balance as u128,
+            AccountType::Stake { staked_amount, .. } if staked_amount > account.lamports => {
+                violations.push(Violation::StakeExceedsLamports {
+                    pubkey: account.pubkey,
+                    staked_amount,
+                    lamports: account.lamports,
+                });
+            }
+            AccountType::Mint { supply, .. } => {
+                mints.insert(account.pubkey, supply);
+            }
+            _ => {}
+        }
+    }
+
+    if supply != expected_supply {
+        violations.push(Violation::SupplyNotConserved {
+            expected: expected_supply,
+            actual: supply,
+        });
+    }
+    for (mint, supply) in mints {
+        let held = held.get(&mint).copied().unwrap_or_default();
+        if held != supply as u128 {
+            violations.push(Violation::TokenSupplyMismatch { mint, supply, held });
+        }
+    }
+
+    VerifyReport { violations }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_accounts_reports_every_violation() {
+        let wallet = Account::new(AccountType::Wallet { balance: 10 });
+        let mint = Account::new(AccountType::Mint {
+            decimals: 0,
+            supply: 5,
+            mint_authority: None,
+            freeze_authority: None,
+        });
+        let token = Account::new(AccountType::TokenAccount {
+            mint: mint.pubkey,
+            token_balance: 5,
+            delegate: None,
+        });
+        let accounts = [&wallet, &mint, &token];
+        let supply: u128 = accounts.iter().map(|acc| acc.lamports as u128).sum();
+        let report = verify_accounts(accounts.iter().map(|acc| (&acc.pubkey, *acc)), supply);
+        assert!(report.is_ok());
+        assert!(report.into_result().is_ok());
+
+        let mut broken = wallet.clone();
+        broken.lamports = 12;
+        let mut stake = Account::new(AccountType::Stake {
+            validator: Pubkey::new_unique(),
+            staked_amount: 50,
+            activation_epoch: None,
+            deactivation_epoch: None,
+        });
+        stake.lamports = 40;
+        let other_key = Pubkey::new_unique();
+        let report = verify_accounts(
+            [
+                (&broken.pubkey, &broken),
+                (&other_key, &stake),
+                (&mint.pubkey, &mint),
+            ]
+            .into_iter(),
+            supply,
+        );
+        assert_eq!(
+            report.violations,
+            [
+                Violation::WalletBalanceMismatch {
+                    pubkey: broken.pubkey,
+                    balance: 10,
+                    lamports: 12,
+                },
+                Violation::KeyMismatch {
+                    key: other_key,
+                    pubkey: stake.pubkey,
+                },
+                Violation::StakeExceedsLamports {
+                    pubkey: stake.pubkey,
+                    staked_amount: 50,
+                    lamports: 40,
+                },
+                Violation::SupplyNotConserved {
+                    expected: supply,
+                    actual: 12 + 40 + mint.lamports as u128,
+                },
+                Violation::TokenSupplyMismatch {
+                    mint: mint.pubkey,
+                    supply: 5,
+                    held: 0,
+                },
+            ]
+        );
+        assert!(matches!(
+            report.into_result(),
+            Err(LedgerError::InvariantViolation(_))
+        ));
+    }
+}
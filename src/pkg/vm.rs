@@ -0,0 +1,396 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::pkg::{
+    account::{Account, AccountKind, AccountType},
+    errors::LedgerError,
+    processor::{credit, debit},
+};
+
+/// Compute units one invocation may use, every executed opcode costs one.
+pub const COMPUTE_BUDGET: u64 = 100_000;
+/// Values the stack holds at most.
+pub const STACK_LIMIT: usize = 256;
+
+/// Opcodes of the program bytecode. Values are `u64`, immediates follow
+/// their opcode in little endian. Account operands are indexes into the
+/// accounts the program is invoked with.
+pub mod op {
+    /// stops the program successfully, as does running past the last byte
+    pub const HALT: u8 = 0x00;
+    /// `PUSH <u64>`
+    pub const PUSH: u8 = 0x01;
+    pub const POP: u8 = 0x02;
+    pub const DUP: u8 = 0x03;
+    pub const SWAP: u8 = 0x04;
+    /// `a b -> a + b`, like every arithmetic opcode the result is checked
+    pub const ADD: u8 = 0x10;
+    pub const SUB: u8 = 0x11;
+    pub const MUL: u8 = 0x12;
+    pub const DIV: u8 = 0x13;
+    pub const MOD: u8 = 0x14;
+    /// `a b -> a < b`, 1 when true and 0 otherwise
+    pub const LT: u8 = 0x15;
+    pub const EQ: u8 = 0x16;
+    pub const NOT: u8 = 0x17;
+    /// `JUMP <u16>` to an absolute offset in the bytecode
+    pub const JUMP: u8 = 0x20;
+    /// `JUMPI <u16>`, `condition ->` jumps when the condition is not 0
+    pub const JUMPI: u8 = 0x21;
+    /// `offset -> value` read from the instruction data
+    pub const INPUT: u8 = 0x30;
+    /// `-> length` of the instruction data
+    pub const INPUT_LEN: u8 = 0x31;
+    /// `account -> lamports`
+    pub const LAMPORTS: u8 = 0x40;
+    /// `account -> signed`, whether the account signed the transaction
+    pub const IS_SIGNER: u8 = 0x41;
+    /// `account offset -> value` read from the data of a program account
+    pub const LOAD: u8 = 0x42;
+    /// `account offset value ->` written to the data of an account the
+    /// program owns
+    pub const STORE: u8 = 0x43;
+    /// `from to amount ->` moves lamports out of an account the program owns
+    /// or out of a wallet that signed the transaction
+    pub const TRANSFER: u8 = 0x44;
+    /// `code ->` fails the program, and the transaction, with `code`
+    pub const ABORT: u8 = 0x50;
+}
+
+/// An account handed to a program.
+#[derive(Debug, Clone)]
+pub struct ProgramAccount {
+    pub account: Account,
+    pub is_signer: bool,
+}
+
+/// Runs `code` on behalf of `program` and returns the compute units it
+/// used. The program reads every account it is given but only writes the
+/// data of the accounts it owns, and debits them or the signing wallets.
+/// A failed run leaves the accounts in an undefined state, the caller
+/// discards them.
+pub fn execute(
+    program: &Pubkey,
+    code: &[u8],
+    accounts: &mut [ProgramAccount],
+    input: &[u8],
+    budget: u64,
+) -> Result<u64, LedgerError> {
+    let mut vm = Vm {
+        program,
+        code,
+        accounts,
+        input,
+        stack: Vec::new(),
+        pc: 0,
+        units: 0,
+    };
+
+    while let Some(&opcode) = vm.code.get(vm.pc) {
+        if vm.units == budget {
+            return Err(LedgerError::ComputeBudgetExceeded {
+                program: program.to_string(),
+                budget,
+            });
+        }
+        vm.units += 1;
+        vm.pc += 1;
+        if opcode == op::HALT {
+            break;
+        }
+        vm.step(opcode)?;
+    }
+
+    Ok(vm.units)
+}
+
+struct Vm<'a> {
+    program: &'a Pubkey,
+    code: &'a [u8],
+    accounts: &'a mut [ProgramAccount],
+    input: &'a [u8],
+    stack: Vec<u64>,
+    /// offset of the next byte to decode
+    pc: usize,
+    units: u64,
+}
+
+impl Vm<'_> {
+    fn step(&mut self, opcode: u8) -> Result<(), LedgerError> {
+        match opcode {
+            op::PUSH => {
+                let value = self.immediate::<8>()?;
+                self.push(u64::from_le_bytes(value))?;
+            }
+            op::POP => {
+                self.pop()?;
+            }
+            op::DUP => {
+                let value = self.pop()?;
+                self.push(value)?;
+                self.push(value)?;
+            }
+            op::SWAP => {
+                let (a, b) = self.pop_pair()?;
+                self.push(b)?;
+                self.push(a)?;
+            }
+            op::ADD => self.arithmetic(u64::checked_add)?,
+            op::SUB => self.arithmetic(u64::checked_sub)?,
+            op::MUL => self.arithmetic(u64::checked_mul)?,
+            op::DIV => self.arithmetic(u64::checked_div)?,
+            op::MOD => self.arithmetic(u64::checked_rem)?,
+            op::LT => self.arithmetic(|a, b| Some((a < b) as u64))?,
+            op::EQ => self.arithmetic(|a, b| Some((a == b) as u64))?,
+            op::NOT => {
+                let value = self.pop()?;
+                self.push((value == 0) as u64)?;
+            }
+            op::JUMP => {
+                self.pc = u16::from_le_bytes(self.immediate::<2>()?) as usize;
+            }
+            op::JUMPI => {
+                let target = u16::from_le_bytes(self.immediate::<2>()?) as usize;
+                if self.pop()? != 0 {
+                    self.pc = target;
+                }
+            }
+            op::INPUT => {
+                let offset = self.pop()?;
+                let value = read_u64(self.input, offset)
+                    .ok_or_else(|| self.fail(format!("input offset {} out of bounds", offset)))?;
+                self.push(value)?;
+            }
+            op::INPUT_LEN => self.push(self.input.len() as u64)?,
+            op::LAMPORTS => {
+                let index = self.pop()?;
+                let lamports = self.account(index)?.account.lamports;
+                self.push(lamports)?;
+            }
+            op::IS_SIGNER => {
+                let index = self.pop()?;
+                let is_signer = self.account(index)?.is_signer;
+                self.push(is_signer as u64)?;
+            }
+            op::LOAD => {
+                let (index, offset) = self.pop_pair()?;
+                let data = self.data(index)?;
+                let value = read_u64(data, offset)
+                    .ok_or_else(|| self.fail(format!("data offset {} out of bounds", offset)))?;
+                self.push(value)?;
+            }
+            op::STORE => {
+                let value = self.pop()?;
+                let (index, offset) = self.pop_pair()?;
+                self.store(index, offset, value)?;
+            }
+            op::TRANSFER => {
+                let amount = self.pop()?;
+                let (from, to) = self.pop_pair()?;
+                self.transfer(from, to, amount)?;
+            }
+            op::ABORT => {
+                let code = self.pop()?;
+                return Err(self.fail(format!("aborted with code {}", code)));
+            }
+            opcode => return Err(self.fail(format!("unknown opcode {:#04x}", opcode))),
+        }
+
+        Ok(())
+    }
+
+    fn fail(&self, reason: String) -> LedgerError {
+        LedgerError::ProgramFailed {
+            program: self.program.to_string(),
+            reason: format!("{} at offset {}", reason, self.pc.saturating_sub(1)),
+        }
+    }
+
+    fn immediate<const N: usize>(&mut self) -> Result<[u8; N], LedgerError> {
+        let bytes = self
+            .code
+            .get(self.pc..self.pc + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| self.fail(String::from("truncated immediate")))?;
+        self.pc += N;
+
+        Ok(bytes)
+    }
+
+    fn push(&mut self, value: u64) -> Result<(), LedgerError> {
+        if self.stack.len() == STACK_LIMIT {
+            return Err(self.fail(String::from("stack overflow")));
+        }
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u64, LedgerError> {
+        self.stack
+            .pop()
+            .ok_or_else(|| self.fail(String::from("stack underflow")))
+    }
+
+    /// Pops `b` then `a`, the values in the order they were pushed.
+    fn pop_pair(&mut self) -> Result<(u64, u64), LedgerError> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        Ok((a, b))
+    }
+
+    fn arithmetic(&mut self, f: impl Fn(u64, u64) -> Option<u64>) -> Result<(), LedgerError> {
+        let (a, b) = self.pop_pair()?;
+        let value = f(a, b).ok_or_else(|| self.fail(String::from("arithmetic overflow")))?;
+        self.push(value)
+    }
+
+    fn account(&self, index: u64) -> Result<&ProgramAccount, LedgerError> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.accounts.get(index))
+            .ok_or_else(|| self.fail(format!("no account at index {}", index)))
+    }
+
+    /// The account at `index` when the program owns it.
+    fn owned_account(&mut self, index: u64) -> Result<&mut Account, LedgerError> {
+        let account = &self.account(index)?.account;
+        if account.owner() != self.program {
+            return Err(self.fail(format!(
+                "account {} is owned by {}",
+                account.pubkey,
+                account.owner()
+            )));
+        }
+
+        Ok(&mut self.accounts[index as usize].account)
+    }
+
+    fn data(&self, index: u64) -> Result<&[u8], LedgerError> {
+        let account = &self.account(index)?.account;
+        match &account.account_type {
+            AccountType::Program { program_data, .. } => Ok(program_data),
+            _ => Err(self.fail(format!("account {} holds no program data", account.pubkey))),
+        }
+    }
+
+    fn store(&mut self, index: u64, offset: u64, value: u64) -> Result<(), LedgerError> {
+        self.owned_account(index)?;
+        let writable = matches!(
+            self.account(index)?.account.account_type,
+            AccountType::Program {
+                executable: false,
+                ..
+            }
+        );
+        if !writable || read_u64(self.data(index)?, offset).is_none() {
+            return Err(self.fail(format!("can not store at data offset {}", offset)));
+        }
+
+        if let AccountType::Program { program_data, .. } =
+            &mut self.accounts[index as usize].account.account_type
+        {
+            program_data[offset as usize..][..8].copy_from_slice(&value.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, from: u64, to: u64, amount: u64) -> Result<(), LedgerError> {
+        self.account(to)?;
+        let source = self.account(from)?;
+        let signing_wallet = source.is_signer && source.account.kind() == AccountKind::Wallet;
+        let source = if signing_wallet {
+            &mut self.accounts[from as usize].account
+        } else {
+            self.owned_account(from)?
+        };
+
+        debit(source, amount)?;
+        credit(&mut self.accounts[to as usize].account, amount)
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: u64) -> Option<u64> {
+    let offset = usize::try_from(offset).ok()?;
+    let bytes = bytes.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_account(owner: Pubkey, data: Vec<u8>) -> ProgramAccount {
+        ProgramAccount {
+            account: Account::new(AccountType::Program {
+                executable: false,
+                program_data: data,
+            })
+            .owned_by(owner),
+            is_signer: false,
+        }
+    }
+
+    fn push(value: u64) -> Vec<u8> {
+        [&[op::PUSH][..], &value.to_le_bytes()].concat()
+    }
+
+    #[test]
+    fn test_vm_counter() {
+        let program = Pubkey::new_unique();
+        // counter += input[0]
+        let code = [
+            push(0),
+            push(0),
+            push(0),
+            push(0),
+            vec![op::LOAD],
+            push(0),
+            vec![op::INPUT, op::ADD, op::STORE],
+        ]
+        .concat();
+
+        let mut accounts = [program_account(program, vec![0; 8])];
+        let input = 5u64.to_le_bytes();
+        let units = execute(&program, &code, &mut accounts, &input, COMPUTE_BUDGET).unwrap();
+        assert_eq!(units, 9);
+        execute(&program, &code, &mut accounts, &input, COMPUTE_BUDGET).unwrap();
+        let AccountType::Program { program_data, .. } = &accounts[0].account.account_type else {
+            panic!("account is not a program account");
+        };
+        assert_eq!(program_data, &10u64.to_le_bytes());
+
+        // owned by another program
+        let mut accounts = [program_account(Pubkey::new_unique(), vec![0; 8])];
+        let err = execute(&program, &code, &mut accounts, &input, COMPUTE_BUDGET).unwrap_err();
+        assert!(matches!(err, LedgerError::ProgramFailed { .. }));
+    }
+
+    #[test]
+    fn test_vm_failures() {
+        let program = Pubkey::new_unique();
+        let run = |code: &[u8], budget| execute(&program, code, &mut [], &[], budget);
+
+        // loops forever
+        let err = run(&[op::JUMP, 0, 0], 1_000).unwrap_err();
+        assert!(matches!(
+            err,
+            LedgerError::ComputeBudgetExceeded { budget: 1_000, .. }
+        ));
+
+        let overflow = [push(u64::MAX), push(1), vec![op::ADD]].concat();
+        let abort = [push(7), vec![op::ABORT]].concat();
+        for code in [
+            &overflow[..],
+            &abort[..],
+            &[op::POP],
+            &[op::PUSH, 1, 2],
+            &[0xff],
+            &[op::LAMPORTS],
+        ] {
+            let err = run(code, COMPUTE_BUDGET).unwrap_err();
+            assert!(matches!(err, LedgerError::ProgramFailed { .. }));
+        }
+
+        assert_eq!(run(&[op::HALT, op::POP], COMPUTE_BUDGET).unwrap(), 1);
+    }
+}